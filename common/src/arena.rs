@@ -0,0 +1,267 @@
+use std::alloc::{alloc_zeroed, dealloc, Layout};
+
+// Every chunk is at least this aligned, larger alignments get a dedicated chunk
+const CHUNK_ALIGN: usize = 16;
+
+#[repr(C)]
+struct Chunk {
+    ptr: *mut u8,
+    size: usize,
+    used: usize,
+    align: usize,
+}
+
+impl Chunk {
+    fn new(size: usize, align: usize) -> Self {
+        let layout = Layout::from_size_align(size, align).expect("Invalid arena chunk layout");
+        let ptr = unsafe { alloc_zeroed(layout) };
+        assert!(!ptr.is_null(), "Failed to allocate {} bytes for arena chunk", size);
+        Chunk {
+            ptr,
+            size,
+            used: 0,
+            align,
+        }
+    }
+
+    // Reserves `layout` in this chunk, None if it doesn't fit
+    fn try_alloc(&mut self, layout: Layout) -> Option<(*mut u8, usize)> {
+        let start = self.ptr as usize + self.used;
+        let aligned = (start + layout.align() - 1) & !(layout.align() - 1);
+        let padding = aligned - start;
+        if self.used + padding + layout.size() > self.size {
+            return None;
+        }
+        self.used += padding + layout.size();
+        Some((aligned as *mut u8, padding + layout.size()))
+    }
+}
+
+/**
+ * Bump allocator living in GameMemory, shared by the runtime and the game lib.
+ *
+ * Memory is handed out from chunks that are never moved, so pointers stay valid while the arena grows
+ * (and across hot reloads). It grows one chunk at a time up to `max_size`, past that allocations fail.
+ * Nothing allocated here gets dropped by the arena, the owner has to drop_in_place what needs it.
+ */
+#[repr(C)]
+pub struct Arena {
+    chunks: Vec<Chunk>,
+    chunk_size: usize,
+    max_size: usize,
+    used: usize, // Bytes handed out, including alignment padding
+    peak: usize,
+    allocations: usize,
+}
+
+impl Arena {
+    // Part of GAME_MEMORY_LAYOUT, list new fields here
+    pub const LAYOUT: u64 = crate::layout_hash(&[
+        crate::struct_layout!(Arena; chunks, chunk_size, max_size, used, peak, allocations) as usize,
+        crate::struct_layout!(Chunk; ptr, size, used, align) as usize,
+    ]);
+
+    pub fn new(chunk_size: usize, max_size: usize) -> Self {
+        assert!(chunk_size <= max_size, "Arena chunk is larger than the arena");
+        Arena {
+            chunks: vec![Chunk::new(chunk_size, CHUNK_ALIGN)],
+            chunk_size,
+            max_size,
+            used: 0,
+            peak: 0,
+            allocations: 0,
+        }
+    }
+
+    /**
+     * Moves `value` into the arena
+     */
+    pub fn alloc<T>(&mut self, value: T) -> Result<*mut T, String> {
+        let ptr = self.alloc_uninit::<T>()?;
+        unsafe { ptr.write(value) };
+        Ok(ptr)
+    }
+
+    /**
+     * Reserves (zeroed) room for a T, for types that are initialized in place
+     */
+    pub fn alloc_uninit<T>(&mut self) -> Result<*mut T, String> {
+        self.alloc_layout(Layout::new::<T>())
+            .map(|ptr| ptr as *mut T)
+            .map_err(|e| format!("{} ({})", e, std::any::type_name::<T>()))
+    }
+
+    /**
+     * Moves every value of `values` into the arena, one after the other
+     */
+    pub fn alloc_iter<T>(&mut self, values: impl ExactSizeIterator<Item = T>) -> Result<*mut [T], String> {
+        let len = values.len();
+        let layout = Layout::array::<T>(len).map_err(|e| format!("{} ({})", e, std::any::type_name::<T>()))?;
+        let ptr = self.alloc_layout(layout)? as *mut T;
+        for (index, value) in values.take(len).enumerate() {
+            unsafe { ptr.add(index).write(value) };
+        }
+        Ok(std::ptr::slice_from_raw_parts_mut(ptr, len))
+    }
+
+    pub fn alloc_layout(&mut self, layout: Layout) -> Result<*mut u8, String> {
+        let current = self.chunks.last_mut().expect("Arena has no chunks");
+        let (ptr, size) = match current.try_alloc(layout) {
+            Some(allocation) => allocation,
+            None => {
+                let chunk_size = self.chunk_size.max(layout.size());
+                if self.capacity() + chunk_size > self.max_size {
+                    return Err(format!(
+                        "Arena out of memory: requested {} bytes, {} of {} bytes in use",
+                        layout.size(),
+                        self.used,
+                        self.max_size
+                    ));
+                }
+                let mut chunk = Chunk::new(chunk_size, layout.align().max(CHUNK_ALIGN));
+                let allocation = chunk.try_alloc(layout).expect("Fresh arena chunk too small");
+                self.chunks.push(chunk);
+                allocation
+            }
+        };
+        self.used += size;
+        self.peak = self.peak.max(self.used);
+        self.allocations += 1;
+        Ok(ptr)
+    }
+
+    /**
+     * Forgets every allocation (without dropping them).
+     * If the arena had to grow, the chunks are merged into one so the next round doesn't grow again.
+     */
+    pub fn reset(&mut self) {
+        if self.chunks.len() > 1 {
+            let capacity = self.capacity();
+            self.free_chunks();
+            self.chunks.push(Chunk::new(capacity, CHUNK_ALIGN));
+        } else {
+            let chunk = &mut self.chunks[0];
+            unsafe { std::ptr::write_bytes(chunk.ptr, 0, chunk.used) };
+            chunk.used = 0;
+        }
+        self.used = 0;
+        self.allocations = 0;
+    }
+
+    fn free_chunks(&mut self) {
+        for chunk in self.chunks.drain(..) {
+            unsafe { dealloc(chunk.ptr, Layout::from_size_align_unchecked(chunk.size, chunk.align)) };
+        }
+    }
+
+    pub fn used(&self) -> usize {
+        self.used
+    }
+    pub fn peak(&self) -> usize {
+        self.peak
+    }
+    pub fn capacity(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.size).sum()
+    }
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+    pub fn allocations(&self) -> usize {
+        self.allocations
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        self.free_chunks();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_allocations_are_aligned() {
+        let mut arena = Arena::new(256, 1024);
+        let byte = arena.alloc(1u8).unwrap();
+        let long = arena.alloc(2u64).unwrap();
+        let short = arena.alloc(3u16).unwrap();
+        let wide = arena.alloc(4u128).unwrap();
+        assert_eq!(long as usize % std::mem::align_of::<u64>(), 0);
+        assert_eq!(short as usize % std::mem::align_of::<u16>(), 0);
+        assert_eq!(wide as usize % std::mem::align_of::<u128>(), 0);
+        unsafe { assert_eq!((*byte, *long, *short, *wide), (1, 2, 3, 4)) };
+        // Padding counts as used
+        assert!(arena.used() > 1 + 8 + 2 + 16);
+    }
+
+    #[test]
+    fn full_chunks_grow_the_arena() {
+        let mut arena = Arena::new(64, 1024);
+        let first = arena.alloc([1u8; 48]).unwrap();
+        let second = arena.alloc([2u8; 48]).unwrap();
+        assert_eq!(arena.chunk_count(), 2);
+        assert_eq!(arena.capacity(), 128);
+        // Earlier allocations don't move
+        unsafe { assert_eq!((*first, *second), ([1u8; 48], [2u8; 48])) };
+
+        // Bigger than a chunk, gets a chunk of its own
+        arena.alloc([3u8; 200]).unwrap();
+        assert_eq!(arena.chunk_count(), 3);
+        assert_eq!(arena.capacity(), 328);
+    }
+
+    #[test]
+    fn allocations_past_the_max_size_fail() {
+        let mut arena = Arena::new(64, 128);
+        arena.alloc([0u8; 64]).unwrap();
+        arena.alloc([0u8; 64]).unwrap();
+        let used = arena.used();
+        assert!(arena.alloc(0u8).unwrap_err().contains("out of memory"));
+        assert!(arena.alloc([0u8; 256]).is_err());
+        assert_eq!(arena.used(), used);
+        assert_eq!(arena.capacity(), 128);
+    }
+
+    #[test]
+    fn reset_merges_the_chunks() {
+        let mut arena = Arena::new(64, 1024);
+        for _ in 0..4 {
+            arena.alloc([7u8; 40]).unwrap();
+        }
+        assert_eq!(arena.chunk_count(), 4);
+        let peak = arena.peak();
+
+        arena.reset();
+        assert_eq!((arena.used(), arena.allocations()), (0, 0));
+        assert_eq!(arena.chunk_count(), 1);
+        assert_eq!(arena.capacity(), 256);
+        assert_eq!(arena.peak(), peak);
+        for _ in 0..4 {
+            arena.alloc([7u8; 40]).unwrap();
+        }
+        assert_eq!(arena.chunk_count(), 1);
+
+        // A single chunk is reused and zeroed
+        arena.reset();
+        let zeroed = arena.alloc_uninit::<[u8; 40]>().unwrap();
+        unsafe { assert_eq!(*zeroed, [0u8; 40]) };
+    }
+
+    #[test]
+    fn alloc_iter_copies_every_value() {
+        let mut arena = Arena::new(256, 1024);
+        let values = arena.alloc_iter((0..10u32).map(|value| value * value)).unwrap();
+        let values = unsafe { &*values };
+        assert_eq!(values.len(), 10);
+        assert_eq!(values[3], 9);
+        assert_eq!(values.as_ptr() as usize % std::mem::align_of::<u32>(), 0);
+
+        let empty = arena.alloc_iter(std::iter::empty::<u64>()).unwrap();
+        assert!(unsafe { &*empty }.is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+pub use sdl2::controller::{Axis as GamepadAxis, Button as GamepadButton};
+
+pub const MAX_GAMEPADS: usize = 4;
+// Sticks report some noise around the center, anything below this counts as 0
+pub const DEFAULT_DEADZONE: f32 = 0.25;
+
+const BUTTON_COUNT: usize = GamepadButton::Touchpad as usize + 1;
+const AXIS_COUNT: usize = GamepadAxis::TriggerRight as usize + 1;
+
+/**
+ * Requested by the game, played (and cleared) by the runtime at the end of the frame
+ */
+#[repr(C)]
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Rumble {
+    pub low_frequency: f32,  // 0..1
+    pub high_frequency: f32, // 0..1
+    pub duration_ms: u32,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+pub struct Pad {
+    pub connected: bool,
+    pub instance_id: u32, // SDL joystick instance id, what controller events refer to
+    pub pressed: [bool; BUTTON_COUNT], // The button was just pressed (one time event)
+    pub held: [bool; BUTTON_COUNT],
+    pub axes: [f32; AXIS_COUNT], // Sticks -1..1 (y up), triggers 0..1, deadzone already applied
+    pub rumble: Option<Rumble>,
+}
+
+/**
+ * Up to MAX_GAMEPADS controllers, a controller keeps its slot until it gets disconnected.
+ * Slot 0 is the first controller plugged in.
+ */
+#[repr(C)]
+pub struct Gamepad {
+    pub pads: [Pad; MAX_GAMEPADS],
+    pub deadzone: f32,
+}
+
+impl Default for Gamepad {
+    fn default() -> Self {
+        Gamepad {
+            pads: [Pad::default(); MAX_GAMEPADS],
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
+
+impl Gamepad {
+    // Part of GAME_MEMORY_LAYOUT, list new fields here
+    pub const LAYOUT: u64 = crate::layout_hash(&[
+        crate::struct_layout!(Gamepad; pads, deadzone) as usize,
+        crate::struct_layout!(Pad; connected, instance_id, pressed, held, axes, rumble) as usize,
+        crate::struct_layout!(Rumble; low_frequency, high_frequency, duration_ms) as usize,
+    ]);
+
+    fn pad(&mut self, instance_id: u32) -> Option<&mut Pad> {
+        self.pads
+            .iter_mut()
+            .find(|pad| pad.connected && pad.instance_id == instance_id)
+    }
+
+    // Runtime side
+
+    // Returns the slot the controller got, None if all of them are taken
+    pub fn connect(&mut self, instance_id: u32) -> Option<usize> {
+        if let Some(slot) = self.slot(instance_id) {
+            return Some(slot);
+        }
+        let slot = self.pads.iter().position(|pad| !pad.connected)?;
+        self.pads[slot] = Pad {
+            connected: true,
+            instance_id,
+            ..Pad::default()
+        };
+        Some(slot)
+    }
+
+    pub fn disconnect(&mut self, instance_id: u32) -> Option<usize> {
+        let slot = self.slot(instance_id)?;
+        self.pads[slot] = Pad::default();
+        Some(slot)
+    }
+
+    pub fn slot(&self, instance_id: u32) -> Option<usize> {
+        self.pads
+            .iter()
+            .position(|pad| pad.connected && pad.instance_id == instance_id)
+    }
+
+    pub fn press(&mut self, instance_id: u32, button: GamepadButton) {
+        if let Some(pad) = self.pad(instance_id) {
+            // Like the keyboard, a press survives a release in the same tick
+            pad.pressed[button as usize] |= !pad.held[button as usize];
+            pad.held[button as usize] = true;
+        }
+    }
+
+    pub fn release(&mut self, instance_id: u32, button: GamepadButton) {
+        if let Some(pad) = self.pad(instance_id) {
+            pad.held[button as usize] = false;
+        }
+    }
+
+    // `value` is the raw SDL axis value
+    pub fn set_axis(&mut self, instance_id: u32, axis: GamepadAxis, value: i16) {
+        let deadzone = self.deadzone;
+        let Some(pad) = self.pad(instance_id) else {
+            return;
+        };
+        let mut value = (value as f32 / i16::MAX as f32).clamp(-1.0, 1.0);
+        // SDL sticks point down on positive y, the game is y up
+        if axis == GamepadAxis::LeftY || axis == GamepadAxis::RightY {
+            value = -value;
+        }
+        // Rescale what's left past the deadzone so the output still covers 0..1
+        let magnitude = ((value.abs() - deadzone) / (1.0 - deadzone)).max(0.0);
+        pad.axes[axis as usize] = magnitude.copysign(value);
+    }
+
+    // Called after every tick, presses are only seen by a single tick
+    pub fn end_tick(&mut self) {
+        for pad in self.pads.iter_mut() {
+            pad.pressed = [false; BUTTON_COUNT];
+        }
+    }
+
+    // Pending rumble for a slot, cleared once taken
+    pub fn take_rumble(&mut self, slot: usize) -> Option<Rumble> {
+        self.pads.get_mut(slot)?.rumble.take()
+    }
+
+    // Game side
+
+    pub fn connected(&self, slot: usize) -> bool {
+        self.pads.get(slot).is_some_and(|pad| pad.connected)
+    }
+
+    pub fn pressed(&self, slot: usize, button: GamepadButton) -> bool {
+        self.pads
+            .get(slot)
+            .is_some_and(|pad| pad.pressed[button as usize])
+    }
+
+    pub fn held(&self, slot: usize, button: GamepadButton) -> bool {
+        self.pads
+            .get(slot)
+            .is_some_and(|pad| pad.held[button as usize])
+    }
+
+    pub fn axis(&self, slot: usize, axis: GamepadAxis) -> f32 {
+        self.pads
+            .get(slot)
+            .map_or(0.0, |pad| pad.axes[axis as usize])
+    }
+
+    // Single player helpers, any connected controller counts
+
+    pub fn any_pressed(&self, button: GamepadButton) -> bool {
+        (0..MAX_GAMEPADS).any(|slot| self.pressed(slot, button))
+    }
+
+    pub fn any_held(&self, button: GamepadButton) -> bool {
+        (0..MAX_GAMEPADS).any(|slot| self.held(slot, button))
+    }
+
+    // Strongest deflection of `axis` across all controllers
+    pub fn any_axis(&self, axis: GamepadAxis) -> f32 {
+        (0..MAX_GAMEPADS)
+            .map(|slot| self.axis(slot, axis))
+            .fold(0.0, |strongest, value| {
+                if value.abs() > strongest.abs() {
+                    value
+                } else {
+                    strongest
+                }
+            })
+    }
+
+    pub fn rumble(&mut self, slot: usize, low_frequency: f32, high_frequency: f32, duration_ms: u32) {
+        if let Some(pad) = self.pads.get_mut(slot).filter(|pad| pad.connected) {
+            pad.rumble = Some(Rumble {
+                low_frequency,
+                high_frequency,
+                duration_ms,
+            });
+        }
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        self.deadzone = deadzone.clamp(0.0, 0.95);
+    }
+}
@@ -0,0 +1,176 @@
+pub use sdl2::keyboard::Scancode;
+
+pub const KEY_COUNT: usize = Scancode::Num as usize;
+// Transitions past this many in a single tick are dropped from the list (the key sets are still right)
+pub const MAX_KEY_TRANSITIONS: usize = 32;
+
+const WORDS: usize = KEY_COUNT.div_ceil(64);
+
+/**
+ * One bit per scancode. Plain data, safe to share between separately compiled binaries.
+ */
+#[repr(C)]
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub struct KeySet {
+    bits: [u64; WORDS],
+}
+
+impl KeySet {
+    pub fn insert(&mut self, key: Scancode) {
+        let index = key as usize;
+        self.bits[index / 64] |= 1 << (index % 64);
+    }
+
+    pub fn remove(&mut self, key: Scancode) {
+        let index = key as usize;
+        self.bits[index / 64] &= !(1 << (index % 64));
+    }
+
+    pub fn contains(&self, key: Scancode) -> bool {
+        let index = key as usize;
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn clear(&mut self) {
+        self.bits = [0; WORDS];
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|word| *word == 0)
+    }
+
+    // In scancode order
+    pub fn iter(&self) -> impl Iterator<Item = Scancode> + '_ {
+        (0..KEY_COUNT)
+            .filter(|index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+            .filter_map(|index| Scancode::from_i32(index as i32))
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy, Debug)]
+pub struct KeyTransition {
+    pub scancode: u16,
+    pub down: bool,
+    pub repeat: bool,   // Generated by the OS key repeat, the key was already down
+    pub timestamp: u32, // SDL event timestamp (ms)
+}
+
+/**
+ * Keyboard state, written by the runtime from SDL events and read by the game once per tick.
+ * Keys are scancodes (physical positions), they don't move around with the keyboard layout.
+ */
+#[repr(C)]
+pub struct Keyboard {
+    pub held: KeySet,
+    pub pressed: KeySet,  // Went down since the last tick (one time event)
+    pub released: KeySet, // Went up since the last tick (one time event)
+    pub repeated: KeySet, // OS key repeat since the last tick, never part of pressed
+    pub changed_at: [u32; KEY_COUNT], // Timestamp of the last down / up of every key
+    transitions: [KeyTransition; MAX_KEY_TRANSITIONS], // Everything that happened since the last tick, in order
+    transition_count: usize,
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Keyboard {
+            held: KeySet::default(),
+            pressed: KeySet::default(),
+            released: KeySet::default(),
+            repeated: KeySet::default(),
+            changed_at: [0; KEY_COUNT],
+            transitions: [KeyTransition::default(); MAX_KEY_TRANSITIONS],
+            transition_count: 0,
+        }
+    }
+}
+
+impl Keyboard {
+    // Part of GAME_MEMORY_LAYOUT, list new fields here
+    pub const LAYOUT: u64 = crate::layout_hash(&[
+        crate::struct_layout!(
+            Keyboard;
+            held,
+            pressed,
+            released,
+            repeated,
+            changed_at,
+            transitions,
+            transition_count,
+        ) as usize,
+        crate::struct_layout!(KeySet; bits) as usize,
+        crate::struct_layout!(KeyTransition; scancode, down, repeat, timestamp) as usize,
+    ]);
+
+    // Runtime side
+
+    pub fn key_down(&mut self, key: Scancode, repeat: bool, timestamp: u32) {
+        if repeat {
+            self.repeated.insert(key);
+        } else if !self.held.contains(key) {
+            self.pressed.insert(key);
+            self.held.insert(key);
+            self.changed_at[key as usize] = timestamp;
+        } else {
+            return; // Down twice without an up in between, nothing changed
+        }
+        self.push_transition(key, true, repeat, timestamp);
+    }
+
+    pub fn key_up(&mut self, key: Scancode, timestamp: u32) {
+        if !self.held.contains(key) {
+            return;
+        }
+        self.held.remove(key);
+        self.released.insert(key);
+        self.changed_at[key as usize] = timestamp;
+        self.push_transition(key, false, false, timestamp);
+    }
+
+    fn push_transition(&mut self, key: Scancode, down: bool, repeat: bool, timestamp: u32) {
+        if self.transition_count < MAX_KEY_TRANSITIONS {
+            self.transitions[self.transition_count] = KeyTransition {
+                scancode: key as u16,
+                down,
+                repeat,
+                timestamp,
+            };
+            self.transition_count += 1;
+        }
+    }
+
+    // Called after every tick, one time events are only seen by a single tick
+    pub fn end_tick(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.repeated.clear();
+        self.transition_count = 0;
+    }
+
+    // Game side
+
+    pub fn pressed(&self, key: Scancode) -> bool {
+        self.pressed.contains(key)
+    }
+
+    pub fn held(&self, key: Scancode) -> bool {
+        self.held.contains(key)
+    }
+
+    pub fn released(&self, key: Scancode) -> bool {
+        self.released.contains(key)
+    }
+
+    // Pressed, or held long enough for the OS to repeat it (menus, text fields)
+    pub fn pressed_or_repeated(&self, key: Scancode) -> bool {
+        self.pressed.contains(key) || self.repeated.contains(key)
+    }
+
+    pub fn pressed_keys(&self) -> impl Iterator<Item = Scancode> + '_ {
+        self.pressed.iter()
+    }
+
+    pub fn transitions(&self) -> &[KeyTransition] {
+        &self.transitions[..self.transition_count]
+    }
+}
@@ -1,5 +1,18 @@
+mod arena;
+mod gamepad;
+mod keyboard;
+mod mouse;
+mod playback;
+mod text;
+
+pub use arena::Arena;
+pub use gamepad::{Gamepad, GamepadAxis, GamepadButton, Rumble, MAX_GAMEPADS};
+pub use keyboard::{KeySet, KeyTransition, Keyboard, Scancode, KEY_COUNT};
+pub use mouse::{Mouse, MouseButton};
+pub use playback::{Playback, SLOWDOWNS};
+pub use text::{TextInput, TEXT_BUFFER_SIZE};
 use imgui::{TextureId, Ui};
-use sdl2::keyboard::Keycode;
+use sdl2::{AudioSubsystem, VideoSubsystem};
 use std::{collections::HashSet, hash::{Hash, Hasher}};
 
 #[macro_export]
@@ -16,50 +29,324 @@ macro_rules! check_gl_errors {
     };
 }
 
-// 4 Kb
-const GAME_MEMORY: usize = 1024 * 8;
+// Persistent memory starts at 8 Kb and can grow up to 1 Mb, past that allocations fail
+const PERSISTENT_CHUNK: usize = 1024 * 8;
+const PERSISTENT_MAX: usize = 1024 * 1024;
+const SCRATCH_CHUNK: usize = 1024 * 16;
+const SCRATCH_MAX: usize = 1024 * 1024;
+// Root pointers into the persistent arena (see GameMemory::roots)
+pub const GAME_MEMORY_ROOTS: usize = 4;
+const RELOAD_SNAPSHOT_SIZE: usize = 1024;
+const FAULT_REPORT_SIZE: usize = 1024 * 16;
 
 #[repr(C)]
 pub struct GameMemory {
     pub initialized: bool,
+    pub time: Time,
+    pub display: Display,
     pub keyboard: Keyboard,
     pub mouse: Mouse,
+    pub gamepad: Gamepad,
+    pub text_input: TextInput,
+    // Pause / single step / slow motion, applied by the runtime before every tick
+    pub playback: Playback,
+    // Save state slots to write / load, set by the runtime hotkeys and cleared by the game once handled
+    pub save_slots: SaveSlots,
+    // Room to start in (--room), read by the game when it builds its state from scratch
+    pub start_room: Option<(i32, i32)>,
     pub debug: Debug,
-    pub storage: [u8; GAME_MEMORY],
+    pub reload_snapshot: ReloadSnapshot,
+    pub fault: Fault,
+    // Fingerprint of the types living in the persistent arena, written by the game lib that created them
+    pub state_layout: u64,
+    // Lives as long as the runtime (survives hot reloads): GameState, Content...
+    pub persistent: Arena,
+    // Reset by the runtime at the start of every frame
+    pub scratch: Arena,
+    // Where the game lib finds its state in the persistent arena again after a reload
+    pub roots: [*mut u8; GAME_MEMORY_ROOTS],
 }
 
 impl GameMemory {
     pub fn default() -> Self {
         Self {
             initialized: false,
+            time: Time::default(),
+            display: Display::default(),
             keyboard: Keyboard::default(),
             debug: Debug::default(),
             mouse: Mouse::default(),
-            storage: [0; GAME_MEMORY],
+            gamepad: Gamepad::default(),
+            text_input: TextInput::default(),
+            playback: Playback::default(),
+            save_slots: SaveSlots::default(),
+            start_room: None,
+            reload_snapshot: ReloadSnapshot::default(),
+            fault: Fault::default(),
+            state_layout: 0,
+            persistent: Arena::new(PERSISTENT_CHUNK, PERSISTENT_MAX),
+            scratch: Arena::new(SCRATCH_CHUNK, SCRATCH_MAX),
+            roots: [std::ptr::null_mut(); GAME_MEMORY_ROOTS],
         }
     }
 }
 
+/**
+ * Layout independent bytes handed from a game lib that is being unloaded to the next one.
+ * Lets the next lib restore some state when it can't reuse storage as-is.
+ */
+#[repr(C)]
+pub struct ReloadSnapshot {
+    len: usize,
+    bytes: [u8; RELOAD_SNAPSHOT_SIZE],
+}
+
+impl Default for ReloadSnapshot {
+    fn default() -> Self {
+        ReloadSnapshot {
+            len: 0,
+            bytes: [0; RELOAD_SNAPSHOT_SIZE],
+        }
+    }
+}
+
+impl ReloadSnapshot {
+    // Returns false (and stores nothing) if the data doesn't fit
+    pub fn write(&mut self, data: &[u8]) -> bool {
+        if data.len() > self.bytes.len() {
+            self.len = 0;
+            return false;
+        }
+        self.bytes[..data.len()].copy_from_slice(data);
+        self.len = data.len();
+        true
+    }
+
+    pub fn read(&self) -> Option<&[u8]> {
+        if self.len == 0 {
+            return None;
+        }
+        Some(&self.bytes[..self.len])
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+/**
+ * Set by the game lib when one of its exported functions panicked (message + backtrace).
+ * While set, the runtime stops calling into the lib until a new one is hot reloaded.
+ */
+#[repr(C)]
+pub struct Fault {
+    len: usize,
+    report: [u8; FAULT_REPORT_SIZE],
+}
+
+impl Default for Fault {
+    fn default() -> Self {
+        Fault {
+            len: 0,
+            report: [0; FAULT_REPORT_SIZE],
+        }
+    }
+}
+
+impl Fault {
+    // Long reports (deep backtraces) are cut to fit
+    pub fn set(&mut self, report: &str) {
+        let mut len = report.len().min(self.report.len());
+        while !report.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.report[..len].copy_from_slice(&report.as_bytes()[..len]);
+        self.len = len;
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.len > 0
+    }
+
+    pub fn report(&self) -> Option<&str> {
+        if self.len == 0 {
+            return None;
+        }
+        std::str::from_utf8(&self.report[..self.len]).ok()
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
 #[repr(C)]
 pub struct GameConfig {
     pub window_width: u32,
     pub window_height: u32,
+    pub game_width: u32, // Resolution the game renders at, the window scales it up
+    pub game_height: u32,
+    pub tick_rate: u32, // Simulation ticks per second
 }
 
-// There is two versions of the static MOUSE/KEYBOARD struct
-// once in the runtime and once in the game dll (statics are not shared!)
-static mut KEYBOARD: *mut Keyboard = std::ptr::null_mut();
-static mut MOUSE: *mut Mouse = std::ptr::null_mut();
+// Written by the runtime before every update_game (tick) and render_game (frame)
+#[repr(C)]
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Time {
+    pub tick_rate: u32,
+    pub tick_delta: f32,  // Seconds simulated by a single tick (1 / tick_rate)
+    pub frame_delta: f32, // Real seconds elapsed since the previous frame
+    pub tick: u64,        // Number of ticks simulated so far
+    pub ticks_this_frame: u32,
+    // How far (0..1) the current frame is between the last tick and the next one.
+    // Rendering should interpolate previous -> current state by this amount
+    pub alpha: f32,
+}
+
+// Written by the runtime whenever the window changes size or goes in / out of fullscreen
+#[repr(C)]
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Display {
+    pub window_size: (u32, u32),   // In screen coordinates (what mouse events use)
+    pub drawable_size: (u32, u32), // In pixels (what the default framebuffer uses), differs on high dpi displays
+    pub fullscreen: bool,
+}
+
+impl Display {
+    /**
+     * Largest integer scale of a `width x height` image that fits the drawable, centered.
+     * Returns (x, y, scale) in drawable pixels, the leftover space is the letterbox.
+     */
+    pub fn letterbox(&self, width: u32, height: u32) -> (i32, i32, u32) {
+        let (drawable_width, drawable_height) = self.drawable_size;
+        let scale = (drawable_width / width).min(drawable_height / height).max(1);
+        let x = (drawable_width as i32 - (width * scale) as i32) / 2;
+        let y = (drawable_height as i32 - (height * scale) as i32) / 2;
+        (x, y, scale)
+    }
+}
+
+// Ctrl+<n> saves slot n, Alt+<n> loads it (1 to 9)
+#[repr(C)]
+#[derive(Default, Clone, Copy, Debug)]
+pub struct SaveSlots {
+    pub save: Option<u8>,
+    pub load: Option<u8>,
+}
+
+// Bump when the GameApi table itself changes (functions added, removed or with new signatures)
+pub const GAME_API_VERSION: u32 = 1;
+
+// Fingerprint of everything the runtime and the game lib share through GameMemory.
+// Both binaries compile their own copy of this constant, if they disagree the layouts differ.
+pub const GAME_MEMORY_LAYOUT: u64 = layout_hash(&[
+    std::mem::size_of::<GameMemory>(),
+    std::mem::align_of::<GameMemory>(),
+    std::mem::offset_of!(GameMemory, initialized),
+    std::mem::offset_of!(GameMemory, time),
+    std::mem::offset_of!(GameMemory, display),
+    std::mem::offset_of!(GameMemory, keyboard),
+    std::mem::offset_of!(GameMemory, mouse),
+    std::mem::offset_of!(GameMemory, gamepad),
+    std::mem::offset_of!(GameMemory, text_input),
+    std::mem::offset_of!(GameMemory, playback),
+    std::mem::offset_of!(GameMemory, save_slots),
+    std::mem::offset_of!(GameMemory, start_room),
+    std::mem::offset_of!(GameMemory, debug),
+    std::mem::offset_of!(GameMemory, reload_snapshot),
+    std::mem::offset_of!(GameMemory, fault),
+    std::mem::offset_of!(GameMemory, state_layout),
+    std::mem::offset_of!(GameMemory, persistent),
+    std::mem::offset_of!(GameMemory, scratch),
+    std::mem::offset_of!(GameMemory, roots),
+    Arena::LAYOUT as usize,
+    Keyboard::LAYOUT as usize,
+    Mouse::LAYOUT as usize,
+    Gamepad::LAYOUT as usize,
+    TextInput::LAYOUT as usize,
+    Playback::LAYOUT as usize,
+    struct_layout!(Fault; len, report) as usize,
+    struct_layout!(ReloadSnapshot; len, bytes) as usize,
+    struct_layout!(Time; tick_rate, tick_delta, frame_delta, tick, ticks_this_frame, alpha) as usize,
+    struct_layout!(Display; window_size, drawable_size, fullscreen) as usize,
+    struct_layout!(SaveSlots; save, load) as usize,
+    struct_layout!(Debug; windows, events) as usize,
+    struct_layout!(GameConfig; window_width, window_height, game_width, game_height, tick_rate) as usize,
+]);
+
+// FNV-1a
+pub const fn layout_hash(values: &[usize]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut i = 0;
+    while i < values.len() {
+        hash = fnv_bytes(hash, &(values[i] as u64).to_le_bytes());
+        i += 1;
+    }
+    hash
+}
 
+// One field in a struct_layout!: its name and offset
+pub const fn field_hash(name: &str, offset: usize) -> usize {
+    let hash = fnv_bytes(0xcbf29ce484222325, name.as_bytes());
+    fnv_bytes(hash, &(offset as u64).to_le_bytes()) as usize
+}
+
+const fn fnv_bytes(mut hash: u64, bytes: &[u8]) -> u64 {
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+        i += 1;
+    }
+    hash
+}
+
+/**
+ * Fingerprint of a struct: size, alignment and the name and offset of every listed field.
+ * struct_layout!(Time; tick, alpha). The fields have to be visible where it's used.
+ */
+#[macro_export]
+macro_rules! struct_layout {
+    ($type:ty; $($field:ident),* $(,)?) => {
+        $crate::layout_hash(&[
+            std::mem::size_of::<$type>(),
+            std::mem::align_of::<$type>(),
+            $($crate::field_hash(stringify!($field), std::mem::offset_of!($type, $field)),)*
+        ])
+    };
+}
+
+/**
+ * Function table exported by the game library through its single `game_api` symbol
+ */
+#[repr(C)]
+pub struct GameApi {
+    pub version: u32,
+    pub memory_layout: u64,
+    pub get_config: extern "C" fn() -> GameConfig,
+    pub init: extern "C" fn(&VideoSubsystem, &AudioSubsystem, *mut GameMemory),
+    pub update: extern "C" fn(),
+    pub render: extern "C" fn(),
+    pub clear_game: extern "C" fn(&mut GameMemory),
+    pub de_init: extern "C" fn(),
+}
+
+pub type GameApiFn = extern "C" fn() -> GameApi;
+
+// There is two versions of the static DEBUG pointer
+// once in the runtime and once in the game dll (statics are not shared!)
 static mut DEBUG: *mut Debug = std::ptr::null_mut();
 
 enum UiElement {
     Text(String),
     Separator,
     Button(fn()),
+    LabeledButton(String, Box<dyn Fn() -> ()>),
     Checkbox(String, bool, Box<dyn Fn() -> ()>),
     SameLine,
     NewLine,
+    // Drawn from the live Playback at render time, the game doesn't update while paused
+    Playback,
     Image(
         u64, // id
         usize, // Texture id
@@ -109,6 +396,10 @@ impl Debug {
         let window = Self::get().windows.last_mut().unwrap();
         window.items.push(UiElement::Button(f));
     }
+    pub fn labeled_button(name: &str, f: Box<dyn Fn() -> ()>) {
+        let window = Self::get().windows.last_mut().unwrap();
+        window.items.push(UiElement::LabeledButton(name.to_string(), f));
+    }
     pub fn image(id: &str, textureId: usize, size: (f32, f32)) {
         return;
         let mut hasher = std::collections::hash_map::DefaultHasher::new();
@@ -163,13 +454,22 @@ impl Debug {
         let window = Self::get().windows.last_mut().unwrap();
         window.items.push(UiElement::Separator);
     }
+    // Pause / step / speed controls for the simulation
+    pub fn playback() {
+        let window = Self::get().windows.last_mut().unwrap();
+        window.items.push(UiElement::Playback);
+    }
     pub fn is_empty() -> bool {
         Self::get().windows.is_empty()
     }
     pub fn clear() {
         Self::get().windows.clear();
     }
-    pub fn render(ui: &Ui) {
+    // Items hold code from the game lib that built them (buttons, checkboxes): leak them when that lib goes away
+    pub fn forget() {
+        std::mem::forget(std::mem::take(&mut Self::get().windows));
+    }
+    pub fn render(ui: &Ui, playback: &mut Playback, time: &Time) {
 
         let debug = Debug::get();
         debug.events.clear();
@@ -194,6 +494,11 @@ impl Debug {
                                     f()
                                 }
                             }
+                            UiElement::LabeledButton(name, f) => {
+                                if ui.button(name) {
+                                    f()
+                                }
+                            }
                             UiElement::Checkbox(name, value, f) => {
                                 if ui.checkbox(name, value) {
                                     f()
@@ -215,157 +520,24 @@ impl Debug {
                             }
                             UiElement::SameLine => ui.same_line(),
                             UiElement::NewLine => ui.new_line(),
+                            UiElement::Playback => {
+                                ui.text(format!("{} - tick {}", playback.describe(), time.tick));
+                                let label = if playback.paused { "Resume (F5)" } else { "Pause (F5)" };
+                                if ui.button(label) {
+                                    playback.toggle_pause();
+                                }
+                                ui.same_line();
+                                if ui.button("Step (F6)") {
+                                    playback.step();
+                                }
+                                ui.same_line();
+                                if ui.button(format!("Speed 1/{} (F7)", playback.slowdown)) {
+                                    playback.cycle_slowdown();
+                                }
+                            }
                         }
                     }
                 });
         }
     }
 }
-
-#[repr(C)]
-#[derive(Default)]
-pub struct Mouse {
-    pub position: (i32, i32),
-    pub wheel: (i32, i32),
-    pub position_rel: (i32, i32),
-    pub left: bool,
-    pub right: bool,
-    pub left_held: bool,
-    pub right_held: bool,
-}
-
-impl Mouse {
-    // Both the game dll and the runtime need to call init
-    // The Keyboard struct lives in the runtime
-    pub fn init(mouse: *mut Mouse) {
-        unsafe {
-            MOUSE = mouse;
-        }
-    }
-    fn get() -> &'static mut Mouse {
-        unsafe { &mut *MOUSE }
-    }
-
-    // Setters
-    pub fn release_left() {
-        let mouse = Self::get();
-        mouse.left = false;
-        mouse.left_held = false;
-    }
-    pub fn press_left() {
-        let mouse = Self::get();
-        mouse.left = !mouse.left_held; // left_held is false on the first iteration, true afterwards
-        mouse.left_held = true;
-    }
-
-    pub fn press_right() {
-        let mouse = Self::get();
-        mouse.right = !mouse.right_held; // right_held is false on the first iteration, true afterwards
-        mouse.right_held = true;
-    }
-    pub fn release_right() {
-        let mouse = Self::get();
-        mouse.right = false;
-        mouse.right_held = false;
-    }
-
-    // Getters
-    pub fn left_pressed() -> bool {
-        Self::get().left
-    }
-    pub fn left_held() -> bool {
-        Self::get().left_held
-    }
-    pub fn right_pressed() -> bool {
-        Self::get().right
-    }
-    pub fn right_held() -> bool {
-        Self::get().right_held
-    }
-
-    pub fn position() -> (i32, i32) {
-        Self::get().position
-    }
-    pub fn position_rel() -> (i32, i32) {
-        Self::get().position_rel
-    }
-    pub fn set_wheel(x: i32, y: i32) {
-        Self::get().wheel = (x, y);
-    }
-    pub fn wheel() -> (i32, i32) {
-        Self::get().wheel
-    }
-
-    pub fn set_position(x: i32, y: i32, xrel: i32, yrel: i32) {
-        Self::get().position = (x, y);
-        Self::get().position_rel.0 += xrel;
-        Self::get().position_rel.1 += yrel;
-    }
-    pub fn clear() {
-        Self::get().position_rel = (0, 0);
-        Self::get().wheel = (0, 0);
-        // Self::get().left = false;
-        // Self::get().right = false;
-        // Self::get().left_held = false;
-        // Self::get().right_held = false;
-    }
-}
-
-#[repr(C)]
-pub struct Keyboard {
-    pub pressed: HashSet<Keycode>, // The key was just pressed (one time event)
-    pub held: HashSet<Keycode>, // The key is being held down for longer period
-}
-
-impl Keyboard {
-    // Both the game dll and the runtime need to call init
-    // The Keyboard struct lives in the runtime
-    pub fn init(keyboard: *mut Keyboard) {
-        unsafe {
-            KEYBOARD = keyboard;
-        }
-    }
-
-    fn get() -> &'static mut Keyboard {
-        unsafe { &mut *KEYBOARD }
-    }
-
-    pub fn clear_pressed() {
-        Self::get().pressed.clear();
-    }
-
-    pub fn release(key: &Keycode) {
-        Self::get().pressed.remove(key);
-        Self::get().held.remove(key);
-    }
-    pub fn press(key: Keycode) {
-        // This will be invoked multiple times if the user holds the key down
-        // On the first iteration we set pressed = !held and held = true
-        // Resulting in pressed = true, held = true on the first pass
-        // and pressed = false, held = true in subsequent passes.
-        let keyboard = Self::get();
-        if keyboard.held.contains(&key) { 
-            keyboard.pressed.remove(&key);
-        } else {
-            keyboard.pressed.insert(key);
-        }
-        Self::get().held.insert(key);
-    }
-
-    pub fn pressed(key: Keycode) -> bool {
-        Self::get().pressed.contains(&key)
-    }
-
-    pub fn held(key: &Keycode) -> bool {
-        Self::get().held.contains(key)
-    }
-}
-
-impl Default for Keyboard {
-    fn default() -> Self {
-        Keyboard {
-            held: HashSet::new(),
-            pressed: HashSet::new(),
-        }
-    }
-}
@@ -0,0 +1,100 @@
+pub use sdl2::mouse::MouseButton;
+
+// MouseButton values go from 0 (Unknown) to 5 (X2)
+const BUTTON_COUNT: usize = MouseButton::X2 as usize + 1;
+
+/**
+ * Mouse state, written by the runtime from SDL events and read by the game once per tick.
+ * Buttons are bitsets indexed by MouseButton.
+ */
+#[repr(C)]
+#[derive(Default, Clone, Copy, Debug)]
+pub struct Mouse {
+    pub position: (i32, i32), // Drawable pixels, origin at the bottom left corner
+    pub position_rel: (i32, i32), // Accumulated since the last tick
+    pub wheel: (i32, i32),        // Accumulated since the last tick
+    pub held: u8,
+    pub pressed: u8,  // Went down since the last tick (one time event)
+    pub released: u8, // Went up since the last tick (one time event)
+    pub changed_at: [u32; BUTTON_COUNT], // SDL timestamp (ms) of the last down / up of every button
+}
+
+fn bit(button: MouseButton) -> u8 {
+    1 << button as u8
+}
+
+impl Mouse {
+    // Part of GAME_MEMORY_LAYOUT, list new fields here
+    pub const LAYOUT: u64 =
+        crate::struct_layout!(Mouse; position, position_rel, wheel, held, pressed, released, changed_at);
+
+    // Runtime side
+
+    pub fn button_down(&mut self, button: MouseButton, timestamp: u32) {
+        if self.held & bit(button) == 0 {
+            self.pressed |= bit(button);
+            self.held |= bit(button);
+            self.changed_at[button as usize] = timestamp;
+        }
+    }
+
+    pub fn button_up(&mut self, button: MouseButton, timestamp: u32) {
+        if self.held & bit(button) != 0 {
+            self.held &= !bit(button);
+            self.released |= bit(button);
+            self.changed_at[button as usize] = timestamp;
+        }
+    }
+
+    pub fn moved(&mut self, x: i32, y: i32, xrel: i32, yrel: i32) {
+        self.position = (x, y);
+        self.position_rel.0 += xrel;
+        self.position_rel.1 += yrel;
+    }
+
+    pub fn scrolled(&mut self, x: i32, y: i32) {
+        self.wheel.0 += x;
+        self.wheel.1 += y;
+    }
+
+    // Called after every tick, one time events are only seen by a single tick
+    pub fn end_tick(&mut self) {
+        self.position_rel = (0, 0);
+        self.wheel = (0, 0);
+        self.pressed = 0;
+        self.released = 0;
+    }
+
+    // Game side
+
+    pub fn pressed(&self, button: MouseButton) -> bool {
+        self.pressed & bit(button) != 0
+    }
+    pub fn held(&self, button: MouseButton) -> bool {
+        self.held & bit(button) != 0
+    }
+    pub fn released(&self, button: MouseButton) -> bool {
+        self.released & bit(button) != 0
+    }
+    pub fn left_pressed(&self) -> bool {
+        self.pressed(MouseButton::Left)
+    }
+    pub fn left_held(&self) -> bool {
+        self.held(MouseButton::Left)
+    }
+    pub fn right_pressed(&self) -> bool {
+        self.pressed(MouseButton::Right)
+    }
+    pub fn right_held(&self) -> bool {
+        self.held(MouseButton::Right)
+    }
+    pub fn position(&self) -> (i32, i32) {
+        self.position
+    }
+    pub fn position_rel(&self) -> (i32, i32) {
+        self.position_rel
+    }
+    pub fn wheel(&self) -> (i32, i32) {
+        self.wheel
+    }
+}
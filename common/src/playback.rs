@@ -0,0 +1,87 @@
+// Slow motion levels, cycled by the runtime hotkey: full speed, 1/2 and 1/4
+pub const SLOWDOWNS: [u32; 3] = [1, 2, 4];
+
+/**
+ * Simulation speed controls. Changed by the runtime hotkeys (F5 pause, F6 step, F7 speed)
+ * and the Debug playback buttons, applied by the runtime before every tick.
+ * Rendering and imgui keep running while the simulation is paused, device input waits for the next tick that runs.
+ */
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Playback {
+    pub paused: bool,
+    pub pending_steps: u32, // Ticks to run while paused
+    pub slowdown: u32,      // Only one in `slowdown` ticks runs
+    skipped: u32,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            paused: false,
+            pending_steps: 0,
+            slowdown: 1,
+            skipped: 0,
+        }
+    }
+}
+
+impl Playback {
+    // Part of GAME_MEMORY_LAYOUT, list new fields here
+    pub const LAYOUT: u64 = crate::struct_layout!(Playback; paused, pending_steps, slowdown, skipped);
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.pending_steps = 0;
+    }
+
+    // Pauses (if needed) and runs exactly one more tick
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.pending_steps += 1;
+    }
+
+    pub fn set_slowdown(&mut self, slowdown: u32) {
+        self.slowdown = slowdown.max(1);
+        self.skipped = 0;
+    }
+
+    pub fn cycle_slowdown(&mut self) {
+        let current = SLOWDOWNS.iter().position(|slowdown| *slowdown == self.slowdown);
+        let next = current.map_or(0, |index| (index + 1) % SLOWDOWNS.len());
+        self.set_slowdown(SLOWDOWNS[next]);
+    }
+
+    // Runtime side: decides if a tick scheduled by the fixed timestep actually runs
+    pub fn should_tick(&mut self) -> bool {
+        if self.paused {
+            if self.pending_steps == 0 {
+                return false;
+            }
+            self.pending_steps -= 1;
+            return true;
+        }
+        self.skipped += 1;
+        if self.skipped < self.slowdown {
+            return false;
+        }
+        self.skipped = 0;
+        true
+    }
+
+    // Interpolation amount for rendering, stretched over all the ticks of a slowed down one
+    pub fn alpha(&self, alpha: f32) -> f32 {
+        if self.paused {
+            return 1.0;
+        }
+        (self.skipped as f32 + alpha) / self.slowdown as f32
+    }
+
+    pub fn describe(&self) -> String {
+        match (self.paused, self.slowdown) {
+            (true, _) => "Paused".to_string(),
+            (false, 1) => "Running".to_string(),
+            (false, slowdown) => format!("Running at 1/{} speed", slowdown),
+        }
+    }
+}
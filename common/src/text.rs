@@ -0,0 +1,103 @@
+// Typed text kept per tick, anything past this is dropped
+pub const TEXT_BUFFER_SIZE: usize = 256;
+
+/**
+ * UTF-8 text typed since the last tick (SDL TextInput) and the IME composition in progress (SDL TextEditing).
+ * Text input is off by default, the game asks for it with start() and the runtime turns it on in SDL.
+ */
+#[repr(C)]
+pub struct TextInput {
+    pub requested: bool, // Set by the game
+    pub active: bool,    // Set by the runtime once SDL text input matches `requested`
+    text: [u8; TEXT_BUFFER_SIZE],
+    text_len: usize,
+    composition: [u8; TEXT_BUFFER_SIZE],
+    composition_len: usize,
+    pub cursor: i32,    // Cursor position inside the composition (in characters)
+    pub selection: i32, // Characters selected after the cursor
+}
+
+impl Default for TextInput {
+    fn default() -> Self {
+        TextInput {
+            requested: false,
+            active: false,
+            text: [0; TEXT_BUFFER_SIZE],
+            text_len: 0,
+            composition: [0; TEXT_BUFFER_SIZE],
+            composition_len: 0,
+            cursor: 0,
+            selection: 0,
+        }
+    }
+}
+
+// Longest prefix of `text` that fits in `size` bytes without splitting a character
+fn truncate(text: &str, size: usize) -> &str {
+    let mut end = text.len().min(size);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+impl TextInput {
+    // Part of GAME_MEMORY_LAYOUT, list new fields here
+    pub const LAYOUT: u64 = crate::struct_layout!(
+        TextInput;
+        requested,
+        active,
+        text,
+        text_len,
+        composition,
+        composition_len,
+        cursor,
+        selection,
+    );
+
+    // Runtime side
+
+    pub fn push_text(&mut self, text: &str) {
+        let text = truncate(text, TEXT_BUFFER_SIZE - self.text_len);
+        self.text[self.text_len..self.text_len + text.len()].copy_from_slice(text.as_bytes());
+        self.text_len += text.len();
+        // Committed text replaces whatever was being composed
+        self.set_composition("", 0, 0);
+    }
+
+    pub fn set_composition(&mut self, text: &str, cursor: i32, selection: i32) {
+        let text = truncate(text, TEXT_BUFFER_SIZE);
+        self.composition[..text.len()].copy_from_slice(text.as_bytes());
+        self.composition_len = text.len();
+        self.cursor = cursor;
+        self.selection = selection;
+    }
+
+    // Called after every tick, typed text is only seen by a single tick. The composition stays until it changes
+    pub fn end_tick(&mut self) {
+        self.text_len = 0;
+    }
+
+    // Game side
+
+    pub fn start(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.requested = false;
+    }
+
+    // True while the game asked for text, keys typed in the meantime shouldn't drive gameplay
+    pub fn is_active(&self) -> bool {
+        self.requested
+    }
+
+    pub fn text(&self) -> &str {
+        std::str::from_utf8(&self.text[..self.text_len]).unwrap_or_default()
+    }
+
+    pub fn composition(&self) -> &str {
+        std::str::from_utf8(&self.composition[..self.composition_len]).unwrap_or_default()
+    }
+}
@@ -0,0 +1,101 @@
+use super::{Component, EntityId, World, WorldOp};
+
+type Command = Box<dyn FnOnce(&mut World)>;
+
+/**
+ * Structural changes queued while the world is only borrowed (iterating a query, all_with...).
+ * Entity ids handed out by spawn() are reserved right away so they can be used in later commands.
+ */
+#[derive(Default)]
+pub(crate) struct CommandQueue {
+    pub(crate) commands: Vec<Command>,
+    // Ids reserved so far: taken from the end of the free list first, then past the last slot
+    pub(crate) reserved_free: usize,
+    pub(crate) reserved_new: u32,
+}
+
+/**
+ * Queues spawn / despawn / insert / remove from code that only has a &World.
+ * Nothing happens until World::apply_commands runs (the sync point), in the order they were queued.
+ */
+pub struct Commands<'w> {
+    world: &'w World,
+}
+
+impl<'w> Commands<'w> {
+    pub(crate) fn new(world: &'w World) -> Self {
+        Commands { world }
+    }
+
+    fn push(&self, command: impl FnOnce(&mut World) + 'static) {
+        self.world.commands.borrow_mut().commands.push(Box::new(command));
+    }
+
+    // The entity exists once the commands are applied, its id can be used right away
+    pub fn spawn(&self) -> EntityId {
+        let id = self.world.reserve_entity();
+        self.push(move |world| {
+            world.add_entity_with_id(id);
+        });
+        id
+    }
+
+    pub fn despawn(&self, entity: EntityId) {
+        self.push(move |world| world.despawn(entity));
+    }
+
+    pub fn insert<T: Component + 'static>(&self, entity: EntityId, component: T) {
+        self.push(move |world| {
+            if let Some(mut entity) = world.entity_mut(entity) {
+                entity.assign(component);
+            }
+        });
+    }
+
+    pub fn remove<T: Component + 'static>(&self, entity: EntityId) {
+        self.push(move |world| world.remove_component::<T>(entity));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[test]
+    fn spawn_reuses_free_slots_first() {
+        let mut world = World::new();
+        let removed = world.add_entity().id;
+        world.remove_entity(removed);
+
+        let reused = world.commands().spawn();
+        let new = world.commands().spawn();
+        assert_eq!(reused.index, removed.index);
+        assert_ne!(reused.generation, removed.generation);
+        assert_eq!(new.index as usize, world.entities.len());
+        world.apply_commands();
+        assert!(world.contains(reused));
+        assert!(world.contains(new));
+        assert!(!world.contains(removed));
+    }
+
+    #[test]
+    fn spawned_entities_exist_after_the_sync_point() {
+        let mut world = World::new();
+        let commands = world.commands();
+        let ids: Vec<EntityId> = (0..3).map(|_| commands.spawn()).collect();
+        commands.insert(ids[0], Health(3));
+        assert_ne!(ids[0], ids[1]);
+        assert_ne!(ids[1], ids[2]);
+        assert_ne!(ids[0], ids[2]);
+        assert!(ids.iter().all(|id| !world.contains(*id)));
+
+        world.apply_commands();
+        assert!(ids.iter().all(|id| world.contains(*id)));
+        assert_eq!(world.find_component::<Health>(ids[0]).unwrap().0, 3);
+        // Reservations start over once applied
+        assert!(!ids.contains(&world.commands().spawn()));
+    }
+}
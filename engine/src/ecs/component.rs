@@ -1,7 +1,7 @@
-use super::Updateable;
+use super::{erased::ErasedBox, EntityId};
 use std::{
-    any::{Any, TypeId},
-    cell::{RefCell, RefMut},
+    any::TypeId,
+    cell::{Cell, RefCell, RefMut},
     collections::HashMap,
 };
 
@@ -16,18 +16,21 @@ pub trait Component {
 
 #[derive(Debug)]
 pub struct ComponentWrapper<T: Component> {
-    pub entity_id: u32,
+    pub entity_id: EntityId,
     pub component: RefCell<T>,
+    // World change ticks of when the component was added / last changed (see World::change_tick)
+    pub added: u32,
+    pub changed: Cell<u32>,
 }
 
 pub struct ComponentStorage<T: Component> {
     pub data: Vec<ComponentWrapper<T>>, // Store components contiguously
-    pub entity_map: HashMap<u32, usize>, // Map entity ID to component index
+    pub entity_map: HashMap<EntityId, usize>, // Map entity ID to component index
     pub type_id: TypeId,
 }
 
 impl<T: Component + 'static> ComponentStorage<T> {
-    pub fn remove_component(&mut self, entity_id: u32) -> Option<T> {
+    pub fn remove_component(&mut self, entity_id: EntityId) -> Option<T> {
         if let Some(index) = self.entity_map.remove(&entity_id) {
             let last_entity_id = self.data.iter().last().unwrap().entity_id;
             let value = self.data.swap_remove(index);
@@ -40,21 +43,45 @@ impl<T: Component + 'static> ComponentStorage<T> {
     }
 }
 
-impl<T: Component + 'static> Updateable for ComponentStorage<T> {
-    fn as_any(&self) -> &dyn Any {
-        self
+// A ComponentStorage<T> of some T, kept in an ErasedBox (not a trait object) so the World survives hot reloads
+pub(crate) struct StorageSlot {
+    storage: ErasedBox,
+    remove: Cell<Option<unsafe fn(&mut ErasedBox, EntityId)>>,
+}
+
+unsafe fn remove_from<T: Component + 'static>(storage: &mut ErasedBox, entity_id: EntityId) {
+    storage.get_mut::<ComponentStorage<T>>().remove_component(entity_id);
+}
+
+impl StorageSlot {
+    pub(crate) fn new<T: Component + 'static>() -> Self {
+        StorageSlot {
+            storage: ErasedBox::new(ComponentStorage::<T>::new(TypeId::of::<T>(), T::CAPACITY)),
+            remove: Cell::new(Some(remove_from::<T>)),
+        }
     }
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self
+
+    // Safety: T has to be the type the slot was created with
+    pub(crate) unsafe fn get<T: Component + 'static>(&self) -> &ComponentStorage<T> {
+        self.remove.set(Some(remove_from::<T>));
+        self.storage.get::<ComponentStorage<T>>()
     }
 
-    fn remove_component(&mut self, entity_id: u32) {
-        if let Some(index) = self.entity_map.remove(&entity_id) {
-            let last_entity_id = self.data.iter().last().unwrap().entity_id;
-            self.data.swap_remove(index);
-            if entity_id != last_entity_id {
-                self.entity_map.insert(last_entity_id, index);
-            }
+    // Safety: T has to be the type the slot was created with
+    pub(crate) unsafe fn get_mut<T: Component + 'static>(&mut self) -> &mut ComponentStorage<T> {
+        self.remove.set(Some(remove_from::<T>));
+        self.storage.get_mut::<ComponentStorage<T>>()
+    }
+
+    pub(crate) fn detach(&self) {
+        self.storage.detach();
+        self.remove.set(None);
+    }
+
+    // Storages no lib has touched since the last detach() keep the component (leaked, nothing finds it)
+    pub(crate) fn remove(&mut self, entity_id: EntityId) {
+        if let Some(remove) = self.remove.get() {
+            unsafe { remove(&mut self.storage, entity_id) }
         }
     }
 }
@@ -68,16 +95,28 @@ impl<T: Component> ComponentStorage<T> {
         }
     }
 
-    // Add a component to the storage and associate it with an entity
-    pub fn add_component(&mut self, entity_id: u32, component: T) {
+    // Add a component to the storage and associate it with an entity, replaces the one it already has
+    pub fn add_component(&mut self, entity_id: EntityId, component: T, tick: u32) {
+        if let Some(index) = self.entity_map.get(&entity_id) {
+            let wrapper = &mut self.data[*index];
+            wrapper.component = RefCell::new(component);
+            wrapper.changed.set(tick);
+            return;
+        }
         self.entity_map.insert(entity_id, self.data.len());
         self.data.push(ComponentWrapper {
             entity_id,
             component: RefCell::new(component),
+            added: tick,
+            changed: Cell::new(tick),
         });
     }
 
-    pub fn find_component(&self, entity_id: u32) -> Option<RefMut<'_, T>> {
+    pub fn find_wrapper(&self, entity_id: EntityId) -> Option<&ComponentWrapper<T>> {
+        self.entity_map.get(&entity_id).map(|index| &self.data[*index])
+    }
+
+    pub fn find_component(&self, entity_id: EntityId) -> Option<RefMut<'_, T>> {
         if let Some(index) = self.entity_map.get(&entity_id) {
             let wrapper = self.data.get(*index);
             return wrapper.map(|wrapper| wrapper.component.borrow_mut());
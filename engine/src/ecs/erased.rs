@@ -0,0 +1,59 @@
+use std::cell::Cell;
+use std::ptr::NonNull;
+
+/**
+ * A boxed value of a type only known at the call sites, with no vtable.
+ * The World lives in memory that outlives the game lib (hot reload), so it can't keep trait objects:
+ * their code is unloaded with the lib that created them. The code that knows the type (here: drop)
+ * is a plain fn pointer instead. detach() forgets it before the lib goes away, any typed access
+ * attaches the loaded lib's code again. Values still detached when the box is dropped are leaked.
+ */
+pub(crate) struct ErasedBox {
+    pointer: NonNull<u8>,
+    drop: Cell<Option<unsafe fn(NonNull<u8>)>>,
+}
+
+unsafe fn drop_box<T>(pointer: NonNull<u8>) {
+    drop(Box::from_raw(pointer.as_ptr() as *mut T));
+}
+
+impl ErasedBox {
+    pub(crate) fn new<T>(value: T) -> Self {
+        let pointer = NonNull::from(Box::leak(Box::new(value))).cast::<u8>();
+        ErasedBox {
+            pointer,
+            drop: Cell::new(Some(drop_box::<T> as unsafe fn(NonNull<u8>))),
+        }
+    }
+
+    // Safety: T has to be the type the box was created with
+    pub(crate) unsafe fn get<T>(&self) -> &T {
+        self.drop.set(Some(drop_box::<T>));
+        &*(self.pointer.as_ptr() as *const T)
+    }
+
+    // Safety: T has to be the type the box was created with
+    pub(crate) unsafe fn get_mut<T>(&mut self) -> &mut T {
+        self.drop.set(Some(drop_box::<T>));
+        &mut *(self.pointer.as_ptr() as *mut T)
+    }
+
+    // Safety: T has to be the type the box was created with
+    pub(crate) unsafe fn into_inner<T>(self) -> T {
+        let value = *Box::from_raw(self.pointer.as_ptr() as *mut T);
+        std::mem::forget(self);
+        value
+    }
+
+    pub(crate) fn detach(&self) {
+        self.drop.set(None);
+    }
+}
+
+impl Drop for ErasedBox {
+    fn drop(&mut self) {
+        if let Some(drop) = self.drop.get() {
+            unsafe { drop(self.pointer) }
+        }
+    }
+}
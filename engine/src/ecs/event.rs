@@ -0,0 +1,210 @@
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+
+use super::erased::ErasedBox;
+
+/**
+ * Double buffered queue of events of one type.
+ * Events live for two updates (World::update_events) so every system gets to see them once,
+ * no matter whether it runs before or after the one sending them.
+ */
+pub struct Events<T> {
+    // Sent before the last update
+    previous: Vec<T>,
+    // Sent since the last update
+    current: Vec<T>,
+    // Number of events sent before the first event in `previous` / `current`
+    previous_start: usize,
+    current_start: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Events {
+            previous: Vec::new(),
+            current: Vec::new(),
+            previous_start: 0,
+            current_start: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    // Swaps the buffers, events that were already in the previous buffer are dropped
+    pub fn update(&mut self) {
+        self.previous_start = self.current_start;
+        self.current_start += self.current.len();
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    // Drops every event, readers skip ahead instead of reading stale ones
+    pub fn clear(&mut self) {
+        self.current_start += self.current.len();
+        self.previous_start = self.current_start;
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    // Total number of events ever sent
+    pub fn count(&self) -> usize {
+        self.current_start + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+
+    // Events sent since `cursor` (an event count) that are still around
+    fn since(&self, cursor: usize) -> impl Iterator<Item = &T> {
+        let previous = cursor.saturating_sub(self.previous_start).min(self.previous.len());
+        let current = cursor.saturating_sub(self.current_start).min(self.current.len());
+        self.previous[previous..].iter().chain(self.current[current..].iter())
+    }
+}
+
+/**
+ * Cursor into an Events<T> queue, kept by the system that reads it.
+ * Every reader sees each event once, as long as it reads at least once every other update.
+ */
+pub struct EventReader<T> {
+    cursor: usize,
+    marker: PhantomData<fn(T)>,
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        EventReader {
+            cursor: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> EventReader<T> {
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        let cursor = self.cursor;
+        self.cursor = events.count();
+        events.since(cursor)
+    }
+}
+
+// Events<T> of one type, kept in an ErasedBox (not a trait object) so the World survives hot reloads
+pub(crate) struct EventQueue {
+    events: ErasedBox,
+    update: Cell<Option<unsafe fn(&ErasedBox)>>,
+    clear: Cell<Option<unsafe fn(&ErasedBox)>>,
+}
+
+unsafe fn update_events<T>(events: &ErasedBox) {
+    events.get::<RefCell<Events<T>>>().borrow_mut().update();
+}
+
+unsafe fn clear_events<T>(events: &ErasedBox) {
+    events.get::<RefCell<Events<T>>>().borrow_mut().clear();
+}
+
+impl EventQueue {
+    pub(crate) fn new<T>() -> Self {
+        let queue = EventQueue {
+            events: ErasedBox::new(RefCell::new(Events::<T>::default())),
+            update: Cell::new(None),
+            clear: Cell::new(None),
+        };
+        queue.attach::<T>();
+        queue
+    }
+
+    // Safety: T has to be the type the queue was created with
+    pub(crate) unsafe fn get<T>(&self) -> &RefCell<Events<T>> {
+        self.attach::<T>();
+        self.events.get::<RefCell<Events<T>>>()
+    }
+
+    fn attach<T>(&self) {
+        self.update.set(Some(update_events::<T>));
+        self.clear.set(Some(clear_events::<T>));
+    }
+
+    pub(crate) fn detach(&self) {
+        self.events.detach();
+        self.update.set(None);
+        self.clear.set(None);
+    }
+
+    // Queues no lib has touched since the last detach() are left as they are
+    pub(crate) fn update(&self) {
+        if let Some(update) = self.update.get() {
+            unsafe { update(&self.events) }
+        }
+    }
+
+    pub(crate) fn clear(&self) {
+        if let Some(clear) = self.clear.get() {
+            unsafe { clear(&self.events) }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::World;
+
+    #[test]
+    fn reader_sees_each_event_once() {
+        let mut events = Events::default();
+        let mut reader = EventReader::default();
+        events.send(1);
+        events.send(2);
+        assert_eq!(reader.read(&events).copied().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(reader.read(&events).count(), 0);
+        events.update();
+        events.send(3);
+        assert_eq!(reader.read(&events).copied().collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn events_are_dropped_after_two_updates() {
+        let mut events = Events::default();
+        let mut late_reader = EventReader::default();
+        events.send("jump");
+        events.update();
+        let mut reader = EventReader::default();
+        assert_eq!(reader.read(&events).count(), 1);
+        events.update();
+        assert!(events.is_empty());
+        assert_eq!(late_reader.read(&events).count(), 0);
+    }
+
+    #[test]
+    fn cleared_events_are_skipped() {
+        let mut events = Events::default();
+        let mut reader = EventReader::default();
+        events.send(1);
+        events.update();
+        events.send(2);
+        events.clear();
+        events.send(3);
+        assert_eq!(reader.read(&events).copied().collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn detached_queues_keep_their_events() {
+        let mut world = World::new();
+        world.add_event::<u32>();
+        world.send_event(7u32);
+        world.detach_lib_code();
+        // Not attached again yet, nothing runs
+        world.update_events();
+        world.add_event::<u32>();
+        let mut reader = EventReader::default();
+        assert_eq!(reader.read(&world.events::<u32>()).copied().collect::<Vec<_>>(), vec![7]);
+        world.update_events();
+        world.update_events();
+        assert!(world.events::<u32>().is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Component, EntityId, With, Without, World, WorldOp};
+
+// The entity this one is attached to, set through World::set_parent
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Parent(pub EntityId);
+impl Component for Parent {}
+
+// Entities attached to this one, kept in sync with their Parent by World::set_parent / remove_parent
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Children(pub Vec<EntityId>);
+impl Component for Children {}
+
+// Position relative to the parent, or to the world for entities without one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub x: f32,
+    pub y: f32,
+}
+impl Component for Transform {}
+
+impl Transform {
+    pub fn new(x: f32, y: f32) -> Self {
+        Transform { x, y }
+    }
+}
+
+// Position in the world, written by World::propagate_transforms
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GlobalTransform {
+    pub x: f32,
+    pub y: f32,
+}
+impl Component for GlobalTransform {}
+
+impl World {
+    /**
+     * Attaches `child` to `parent`, detaching it from its previous parent first.
+     * Does nothing if either entity is gone, panics if it would make a cycle.
+     */
+    pub fn set_parent(&mut self, child: EntityId, parent: EntityId) {
+        if !self.contains(child) || !self.contains(parent) {
+            return;
+        }
+        let mut ancestor = Some(parent);
+        while let Some(entity) = ancestor {
+            assert!(entity != child, "Entity {:?} can't be its own ancestor", child);
+            ancestor = self.find_component::<Parent>(entity).map(|parent| parent.0);
+        }
+
+        self.remove_parent(child);
+        if let Some(mut entity) = self.entity_mut(child) {
+            entity.assign(Parent(parent));
+        }
+        let added = match self.find_component::<Children>(parent) {
+            Some(mut children) => {
+                children.0.push(child);
+                true
+            }
+            None => false,
+        };
+        if added {
+            self.mark_changed::<Children>(parent);
+        } else if let Some(mut entity) = self.entity_mut(parent) {
+            entity.assign(Children(vec![child]));
+        }
+    }
+
+    // Detaches `child` from its parent, the child stays in the world
+    pub fn remove_parent(&mut self, child: EntityId) -> Option<EntityId> {
+        let parent = self.unassign::<Parent>(child)?.0;
+        if let Some(mut children) = self.find_component::<Children>(parent) {
+            children.0.retain(|entity| *entity != child);
+        }
+        self.mark_changed::<Children>(parent);
+        Some(parent)
+    }
+
+    // Ids of the entities attached to `entity`
+    pub fn children(&self, entity: EntityId) -> Vec<EntityId> {
+        self.find_component::<Children>(entity)
+            .map(|children| children.0.clone())
+            .unwrap_or_default()
+    }
+
+    /**
+     * Writes the GlobalTransform of every entity in a hierarchy: roots (a Transform, no Parent)
+     * copy their Transform, children add theirs to the parent's. Entities without one get a GlobalTransform.
+     */
+    pub fn propagate_transforms(&mut self) {
+        let mut pending: Vec<(EntityId, GlobalTransform)> = self
+            .query_filtered::<(EntityId,), (With<Transform>, Without<Parent>)>(0)
+            .map(|(entity,)| (entity, GlobalTransform::default()))
+            .collect();
+        while let Some((entity, parent)) = pending.pop() {
+            let local = self.find_component::<Transform>(entity).map(|transform| *transform).unwrap_or_default();
+            let global = GlobalTransform {
+                x: parent.x + local.x,
+                y: parent.y + local.y,
+            };
+            let current = self.find_component::<GlobalTransform>(entity).map(|transform| *transform);
+            match current {
+                Some(current) if current == global => {}
+                Some(_) => {
+                    *self.find_component::<GlobalTransform>(entity).unwrap() = global;
+                    self.mark_changed::<GlobalTransform>(entity);
+                }
+                None => {
+                    if let Some(mut entity) = self.entity_mut(entity) {
+                        entity.assign(global);
+                    }
+                }
+            }
+            for child in self.children(entity) {
+                pending.push((child, global));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn(world: &mut World, x: f32, y: f32) -> EntityId {
+        let mut entity = world.add_entity();
+        entity.assign(Transform::new(x, y));
+        entity.id
+    }
+
+    #[test]
+    fn set_parent_moves_the_child() {
+        let mut world = World::new();
+        let old = spawn(&mut world, 0.0, 0.0);
+        let new = spawn(&mut world, 0.0, 0.0);
+        let child = spawn(&mut world, 0.0, 0.0);
+
+        world.set_parent(child, old);
+        assert_eq!(world.children(old), vec![child]);
+        world.set_parent(child, new);
+        assert!(world.children(old).is_empty());
+        assert_eq!(world.children(new), vec![child]);
+        assert_eq!(*world.find_component::<Parent>(child).unwrap(), Parent(new));
+    }
+
+    #[test]
+    #[should_panic(expected = "its own ancestor")]
+    fn set_parent_refuses_cycles() {
+        let mut world = World::new();
+        let root = spawn(&mut world, 0.0, 0.0);
+        let child = spawn(&mut world, 0.0, 0.0);
+        world.set_parent(child, root);
+        world.set_parent(root, child);
+    }
+
+    #[test]
+    fn remove_parent_keeps_the_child() {
+        let mut world = World::new();
+        let root = spawn(&mut world, 0.0, 0.0);
+        let child = spawn(&mut world, 0.0, 0.0);
+        world.set_parent(child, root);
+
+        assert_eq!(world.remove_parent(child), Some(root));
+        assert!(world.contains(child));
+        assert!(world.find_component::<Parent>(child).is_none());
+        assert!(world.children(root).is_empty());
+        assert_eq!(world.remove_parent(child), None);
+    }
+
+    #[test]
+    fn transforms_add_up_down_the_hierarchy() {
+        let mut world = World::new();
+        let root = spawn(&mut world, 10.0, 20.0);
+        let child = spawn(&mut world, 1.0, 2.0);
+        let grandchild = spawn(&mut world, 0.5, -4.0);
+        world.set_parent(child, root);
+        world.set_parent(grandchild, child);
+
+        world.propagate_transforms();
+        let global = |world: &World, entity| *world.find_component::<GlobalTransform>(entity).unwrap();
+        assert_eq!(global(&world, root), GlobalTransform { x: 10.0, y: 20.0 });
+        assert_eq!(global(&world, child), GlobalTransform { x: 11.0, y: 22.0 });
+        assert_eq!(global(&world, grandchild), GlobalTransform { x: 11.5, y: 18.0 });
+
+        world.find_component::<Transform>(root).unwrap().x = 0.0;
+        world.propagate_transforms();
+        assert_eq!(global(&world, grandchild), GlobalTransform { x: 1.5, y: 18.0 });
+    }
+
+    #[test]
+    fn removing_an_entity_removes_its_descendants() {
+        let mut world = World::new();
+        let root = spawn(&mut world, 0.0, 0.0);
+        let entity = spawn(&mut world, 0.0, 0.0);
+        let child = spawn(&mut world, 0.0, 0.0);
+        let grandchild = spawn(&mut world, 0.0, 0.0);
+        let sibling = spawn(&mut world, 0.0, 0.0);
+        world.set_parent(entity, root);
+        world.set_parent(sibling, root);
+        world.set_parent(child, entity);
+        world.set_parent(grandchild, child);
+
+        world.remove_entity(entity);
+        assert!(!world.contains(entity));
+        assert!(!world.contains(child));
+        assert!(!world.contains(grandchild));
+        assert!(world.contains(sibling));
+        assert_eq!(world.children(root), vec![sibling]);
+    }
+}
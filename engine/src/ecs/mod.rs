@@ -1,59 +1,96 @@
+pub mod commands;
 pub mod component;
+mod erased;
+pub mod event;
+pub mod hierarchy;
+pub mod query;
+pub mod reflect;
+pub mod schedule;
+pub mod snapshot;
 
-use std::any::{Any, TypeId};
-use std::cell::{RefCell, RefMut};
+use std::any::TypeId;
+use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::collections::HashMap;
 use std::fmt::Debug;
-use std::rc::Rc;
 
+use commands::CommandQueue;
+use component::StorageSlot;
+use erased::ErasedBox;
+use event::EventQueue;
+
+pub use commands::Commands;
 pub use component::{Component, ComponentStorage, ComponentWrapper};
-use rand::Rng;
+pub use event::{EventReader, Events};
+pub use hierarchy::{Children, GlobalTransform, Parent, Transform};
+pub use query::{Added, Changed, LastRun, Mut, Query, QueryFilter, QueryIter, QueryParam, With, Without};
+pub use schedule::{Schedule, Stage, System, SystemConfig, STAGES};
+pub use reflect::ComponentRegistry;
+pub use snapshot::WorldSnapshot;
+use serde::{Deserialize, Serialize};
+
+/**
+ * Handle to an entity: a slot index plus the generation of that slot.
+ * Slots are reused once their entity is removed, the generation tells an old handle
+ * apart from the entity now living in the same slot.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EntityId {
+    pub index: u32,
+    pub generation: u32,
+}
 
-pub type Resource = Rc<RefCell<Box<dyn Any>>>;
 // Internal entity (no world reference - self ref not allowed in Rust)
 // TODO: should this be pub?
 #[derive(Clone, Copy, Debug)]
 pub struct IEntity {
-    id: u32,
+    id: EntityId,
 }
 
 pub struct Entity<'a> {
-    pub id: u32,
+    pub id: EntityId,
     pub world: &'a World,
 }
 
 pub struct EntityMut<'a> {
-    pub id: u32,
+    pub id: EntityId,
     pub world: &'a mut World,
 }
 
+#[derive(Clone, Copy, Debug)]
+struct EntitySlot {
+    generation: u32, // Bumped every time the slot is freed
+    alive: bool,
+}
+
 // World struct that manages entities and component storages
 pub struct World {
     pub entity_count: u32,
-    // todo: make entities private again, maybe even remove?????
-    entities: Vec<IEntity>,
-    components: HashMap<TypeId, Box<dyn Updateable>>,
-    resources: HashMap<TypeId, Resource>,
-}
-
-trait Updateable {
-    // I have no idea how / why this works
-    fn as_any(&self) -> &dyn Any;
-    fn as_any_mut(&mut self) -> &mut dyn Any;
-
-    fn remove_component(&mut self, entity_id: u32);
+    entities: Vec<EntitySlot>,
+    // Indices of dead slots, reused before the slot list grows
+    free: Vec<u32>,
+    components: HashMap<TypeId, StorageSlot>,
+    // One RefCell<T> per resource type, in an ErasedBox so they survive hot reloads
+    resources: HashMap<TypeId, ErasedBox>,
+    // One Events<T> per event type, added with add_event
+    events: HashMap<TypeId, EventQueue>,
+    // Stamped on components as they are added / changed, see LastRun
+    change_tick: Cell<u32>,
+    // Queued by Commands, applied by apply_commands
+    commands: RefCell<CommandQueue>,
 }
 
+// Lookups with a handle to an entity that was removed (a stale handle) find nothing
 pub trait WorldOp {
     fn add_entity<'a>(&'a mut self) -> EntityMut<'_>;
-    fn remove_entity<'a>(&'a mut self, entity: u32);
+    fn remove_entity<'a>(&'a mut self, entity: EntityId);
+    fn contains(&self, entity: EntityId) -> bool;
 
     fn add_component<T: Component + 'static>(&mut self, entity: &IEntity, component: T);
-    fn remove_component<T: Component + 'static>(&mut self, entity: u32);
-    fn find_component<'a, T: Component + 'static>(&'a self, entity: u32) -> Option<RefMut<'a, T>>;
+    fn remove_component<T: Component + 'static>(&mut self, entity: EntityId);
+    fn find_component<'a, T: Component + 'static>(&'a self, entity: EntityId) -> Option<RefMut<'a, T>>;
 
-    fn entity(&self, entity: u32) -> Entity<'_>;
-    fn entity_mut(&mut self, entity: u32) -> EntityMut<'_>;
+    fn entity(&self, entity: EntityId) -> Option<Entity<'_>>;
+    fn entity_mut(&mut self, entity: EntityId) -> Option<EntityMut<'_>>;
 
     fn first<'a, T: Component + 'static>(&'a self) -> Option<Entity<'_>>;
     fn all_with<T: Component + 'static>(&self) -> Box<dyn Iterator<Item = Entity<'_>> + '_>;
@@ -66,135 +103,377 @@ impl World {
     pub fn new() -> Self {
         World {
             entities: Vec::new(),
+            free: Vec::new(),
             entity_count: 0,
             components: HashMap::with_capacity(64),
             resources: HashMap::with_capacity(8),
+            events: HashMap::with_capacity(8),
+            change_tick: Cell::new(1),
+            commands: RefCell::new(CommandQueue::default()),
+        }
+    }
+
+    // Replaces (and returns) the resource of the same type if there is one
+    pub fn add_resource<T: 'static>(&mut self, resource: T) -> Option<T> {
+        let previous = self.remove_resource::<T>();
+        self.resources.insert(TypeId::of::<T>(), ErasedBox::new(RefCell::new(resource)));
+        previous
+    }
+
+    // Adds T::default() unless there already is a T
+    pub fn init_resource<T: Default + 'static>(&mut self) {
+        if !self.has_resource::<T>() {
+            self.add_resource(T::default());
+        }
+    }
+
+    pub fn has_resource<T: 'static>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
+    // Nothing can be borrowing it, that takes a &World
+    pub fn remove_resource<T: 'static>(&mut self) -> Option<T> {
+        let resource = self.resources.remove(&TypeId::of::<T>())?;
+        // Keyed by TypeId::of::<T>()
+        Some(unsafe { resource.into_inner::<RefCell<T>>() }.into_inner())
+    }
+
+    fn resource_cell<T: 'static>(&self) -> Option<&RefCell<T>> {
+        let resource = self.resources.get(&TypeId::of::<T>())?;
+        // Keyed by TypeId::of::<T>(), attaches this lib's code to a resource that survived a reload
+        Some(unsafe { resource.get::<RefCell<T>>() })
+    }
+
+    // None if there is no T, or if it is borrowed through resource_mut
+    pub fn get_resource<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        self.resource_cell::<T>()?.try_borrow().ok()
+    }
+
+    // None if there is no T, or if it is already borrowed
+    pub fn get_resource_mut<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+        self.resource_cell::<T>()?.try_borrow_mut().ok()
+    }
+
+    // Panics if there is no T, or if it is borrowed through resource_mut
+    pub fn resource<T: 'static>(&self) -> Ref<'_, T> {
+        self.get_resource::<T>().unwrap_or_else(|| self.missing_resource::<T>())
+    }
+
+    // Panics if there is no T, or if it is already borrowed
+    pub fn resource_mut<T: 'static>(&self) -> RefMut<'_, T> {
+        self.get_resource_mut::<T>().unwrap_or_else(|| self.missing_resource::<T>())
+    }
+
+    fn missing_resource<T: 'static>(&self) -> ! {
+        if self.has_resource::<T>() {
+            panic!("Resource {} is already borrowed", std::any::type_name::<T>())
         }
+        panic!(
+            "Resource {} is not in the world (World::add_resource / init_resource)",
+            std::any::type_name::<T>()
+        )
+    }
+
+    // Registers the Events<T> queue, adding it again keeps the queued events
+    pub fn add_event<T: 'static>(&mut self) {
+        let queue = self.events.entry(TypeId::of::<T>()).or_insert_with(EventQueue::new::<T>);
+        // Attaches this lib's code to a queue that survived a reload
+        unsafe { queue.get::<T>() };
     }
 
-    pub fn add_resource<T: Any + 'static>(&mut self, resource: T) {
-        self.resources.insert(
-            resource.type_id(),
-            Rc::new(RefCell::new(Box::new(resource))),
-        );
+    fn event_queue<T: 'static>(&self) -> &RefCell<Events<T>> {
+        let queue = self.events.get(&TypeId::of::<T>()).unwrap_or_else(|| {
+            panic!(
+                "Event {} was never added to the world (World::add_event)",
+                std::any::type_name::<T>()
+            )
+        });
+        // Keyed by TypeId::of::<T>()
+        unsafe { queue.get::<T>() }
     }
-    pub fn unassign<T: Component + 'static>(&mut self) -> T {
-        todo!()
+
+    // Can be called while iterating the world, readers see the event until the second update
+    pub fn send_event<T: 'static>(&self, event: T) {
+        self.event_queue::<T>().borrow_mut().send(event);
+    }
+
+    // Read through an EventReader, see Events
+    pub fn events<T: 'static>(&self) -> Ref<'_, Events<T>> {
+        self.event_queue::<T>().borrow()
+    }
+
+    // Once per frame, before any system sends or reads events
+    pub fn update_events(&mut self) {
+        for queue in self.events.values() {
+            queue.update();
+        }
+    }
+
+    /**
+     * Forgets the code the world keeps for types it only knows at runtime (component storages, resources, event queues,
+     * queued commands), it belongs to the game lib that stored it. Call it first thing after a hot reload,
+     * before that code could run: add_event, ComponentRegistry::attach and any typed access attach the new lib's code again.
+     * Commands that were still queued are dropped (leaked).
+     */
+    pub fn detach_lib_code(&mut self) {
+        std::mem::forget(std::mem::take(self.commands.get_mut()));
+        for storage in self.components.values() {
+            storage.detach();
+        }
+        for resource in self.resources.values() {
+            resource.detach();
+        }
+        for queue in self.events.values() {
+            queue.detach();
+        }
+    }
+
+    // Removes every entity and component and drops queued events, resources stay
+    pub fn clear(&mut self) {
+        *self.commands.get_mut() = CommandQueue::default();
+        for queue in self.events.values() {
+            queue.clear();
+        }
+        self.entities.clear();
+        self.free.clear();
+        self.components.clear();
+        self.entity_count = 0;
+    }
+
+    /**
+     * Adds an entity with a known id (restoring a snapshot), components refer to entities by id.
+     * Panics if the slot is already taken.
+     */
+    pub fn add_entity_with_id(&mut self, id: EntityId) -> EntityMut<'_> {
+        while self.entities.len() <= id.index as usize {
+            self.free.push(self.entities.len() as u32);
+            self.entities.push(EntitySlot {
+                generation: 0,
+                alive: false,
+            });
+        }
+        let slot = &mut self.entities[id.index as usize];
+        assert!(!slot.alive, "Entity slot {} is already in use", id.index);
+        slot.generation = id.generation;
+        slot.alive = true;
+        self.free.retain(|index| *index != id.index);
+        self.entity_count += 1;
+        EntityMut { id, world: self }
+    }
+
+    pub fn entity_ids(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.alive)
+            .map(|(index, slot)| EntityId {
+                index: index as u32,
+                generation: slot.generation,
+            })
+    }
+
+    // Removes the entity's T and hands it back
+    pub fn unassign<T: Component + 'static>(&mut self, entity: EntityId) -> Option<T> {
+        self.extract_component::<T>(entity)
+    }
+
+    pub fn commands(&self) -> Commands<'_> {
+        Commands::new(self)
+    }
+
+    /**
+     * Sync point: runs everything queued through commands() since the last call.
+     * add_entity / remove_entity call this first so ids reserved by Commands::spawn stay free.
+     */
+    pub fn apply_commands(&mut self) {
+        let queue = std::mem::take(self.commands.get_mut());
+        for command in queue.commands {
+            command(self);
+        }
+    }
+
+    // Next free id that isn't reserved yet, see Commands::spawn
+    fn reserve_entity(&self) -> EntityId {
+        let mut queue = self.commands.borrow_mut();
+        if queue.reserved_free < self.free.len() {
+            let index = self.free[self.free.len() - 1 - queue.reserved_free];
+            queue.reserved_free += 1;
+            return EntityId {
+                index,
+                generation: self.entities[index as usize].generation,
+            };
+        }
+        let index = self.entities.len() as u32 + queue.reserved_new;
+        queue.reserved_new += 1;
+        EntityId { index, generation: 0 }
+    }
+
+    // Children go with their parent, all the way down
+    fn despawn(&mut self, entity: EntityId) {
+        if !self.contains(entity) {
+            return;
+        }
+        self.remove_parent(entity);
+        if let Some(children) = self.extract_component::<Children>(entity) {
+            for child in children.0 {
+                self.despawn(child);
+            }
+        }
+        self.entity_count -= 1;
+        for storage in self.components.values_mut() {
+            storage.remove(entity);
+        }
+        let slot = &mut self.entities[entity.index as usize];
+        slot.alive = false;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(entity.index);
     }
 
     // Register a new component type with an empty storage
     fn register_component<T: Component + 'static>(&mut self) {
-        let type_id = TypeId::of::<T>();
-        self.components.insert(
-            type_id,
-            Box::new(ComponentStorage::<T>::new(type_id, T::CAPACITY)),
-        );
+        self.components.insert(TypeId::of::<T>(), StorageSlot::new::<T>());
     }
 
-    pub fn extract_component<T: Component + 'static>(&mut self, entity_id: u32) -> Option<T> {
-        let type_id = TypeId::of::<T>();
-        if let Some(storage) = self.components.get_mut(&type_id) {
-            if let Some(storage) = storage.as_any_mut().downcast_mut::<ComponentStorage<T>>() {
-                return storage.remove_component(entity_id);
-            }
+    pub fn change_tick(&self) -> u32 {
+        self.change_tick.get()
+    }
+
+    // Returns the current tick, changes from now on get the next one
+    pub fn increment_change_tick(&self) -> u32 {
+        let tick = self.change_tick.get();
+        self.change_tick.set(tick + 1);
+        tick
+    }
+
+    // Changes through Entity::get / find_component aren't tracked, systems that rely on Changed<T> call this
+    pub fn mark_changed<T: Component + 'static>(&self, entity: EntityId) {
+        if let Some(wrapper) = self.storage::<T>().and_then(|storage| storage.find_wrapper(entity)) {
+            wrapper.changed.set(self.change_tick());
         }
-        return None;
+    }
+
+    // Slots are keyed by TypeId::of::<T>()
+    fn storage<T: Component + 'static>(&self) -> Option<&ComponentStorage<T>> {
+        self.components
+            .get(&TypeId::of::<T>())
+            .map(|storage| unsafe { storage.get::<T>() })
+    }
+
+    fn storage_mut<T: Component + 'static>(&mut self) -> Option<&mut ComponentStorage<T>> {
+        self.components
+            .get_mut(&TypeId::of::<T>())
+            .map(|storage| unsafe { storage.get_mut::<T>() })
+    }
+
+    pub fn extract_component<T: Component + 'static>(&mut self, entity_id: EntityId) -> Option<T> {
+        self.storage_mut::<T>()?.remove_component(entity_id)
+    }
+}
+
+// Lets a Schedule run straight on a World
+impl AsMut<World> for World {
+    fn as_mut(&mut self) -> &mut World {
+        self
     }
 }
 
 impl WorldOp for World {
     // Add a new entity to the world and return it
     fn add_entity(&mut self) -> EntityMut<'_> {
-        // let id = self.entities.len() as u32;
+        self.apply_commands();
         self.entity_count = self.entity_count + 1;
-        let rng: u32 = rand::thread_rng().r#gen();
-        self.entities.push(IEntity { id: rng });
-        return EntityMut {
-            id: rng,
-            world: self,
+        let id = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.entities[index as usize];
+                slot.alive = true;
+                EntityId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.entities.push(EntitySlot {
+                    generation: 0,
+                    alive: true,
+                });
+                EntityId {
+                    index: self.entities.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
         };
+        return EntityMut { id, world: self };
     }
-    fn remove_entity<'a>(&'a mut self, entity: u32) {
-        self.entity_count -= 1;
-        for (_, updatable) in self.components.iter_mut() {
-            updatable.remove_component(entity);
-        }
-        self.entities.retain(|e| e.id != entity);
+    // Removing a stale handle does nothing
+    fn remove_entity<'a>(&'a mut self, entity: EntityId) {
+        self.apply_commands();
+        self.despawn(entity);
     }
 
-    fn entity(&self, entity: u32) -> Entity<'_> {
-        return Entity {
+    fn contains(&self, entity: EntityId) -> bool {
+        self.entities
+            .get(entity.index as usize)
+            .is_some_and(|slot| slot.alive && slot.generation == entity.generation)
+    }
+
+    fn entity(&self, entity: EntityId) -> Option<Entity<'_>> {
+        if !self.contains(entity) {
+            return None;
+        }
+        return Some(Entity {
             id: entity,
             world: self,
-        };
+        });
     }
 
-    fn entity_mut(&mut self, entity: u32) -> EntityMut<'_> {
-        return EntityMut {
+    fn entity_mut(&mut self, entity: EntityId) -> Option<EntityMut<'_>> {
+        if !self.contains(entity) {
+            return None;
+        }
+        return Some(EntityMut {
             id: entity,
             world: self,
-        };
+        });
     }
 
-    fn find_component<T: Component + 'static>(&self, entity: u32) -> Option<RefMut<'_, T>> {
-        let type_id = TypeId::of::<T>();
-        if let Some(storage) = self.components.get(&type_id) {
-            if let Some(storage) = storage.as_any().downcast_ref::<ComponentStorage<T>>() {
-                return storage.find_component(entity);
-            }
-        }
-        return None;
+    fn find_component<T: Component + 'static>(&self, entity: EntityId) -> Option<RefMut<'_, T>> {
+        self.storage::<T>()?.find_component(entity)
     }
 
     // Add a component to the specified entity's component storage
-    fn remove_component<T: Component + 'static>(&mut self, entity: u32) {
-        let type_id = TypeId::of::<T>();
-        if let Some(storage) = self.components.get_mut(&type_id) {
-            if let Some(storage) = storage.as_any_mut().downcast_mut::<ComponentStorage<T>>() {
-                storage.remove_component(entity);
-            }
+    fn remove_component<T: Component + 'static>(&mut self, entity: EntityId) {
+        if let Some(storage) = self.storage_mut::<T>() {
+            storage.remove_component(entity);
         }
     }
     // Add a component to the specified entity's component storage
+    // Components for stale handles are dropped
     fn add_component<T: Component + 'static>(&mut self, entity: &IEntity, component: T) {
+        if !self.contains(entity.id) {
+            return;
+        }
         let type_id = TypeId::of::<T>();
         if let None = self.components.get(&type_id) {
             self.register_component::<T>();
         }
-        if let Some(storage) = self.components.get_mut(&type_id) {
-            if let Some(storage) = storage.as_any_mut().downcast_mut::<ComponentStorage<T>>() {
-                storage.add_component(entity.id, component)
-            }
+        let tick = self.change_tick.get();
+        if let Some(storage) = self.storage_mut::<T>() {
+            storage.add_component(entity.id, component, tick)
         }
     }
 
     fn first<'a, T: Component + 'static>(&'a self) -> Option<Entity<'a>> {
-        let type_id = TypeId::of::<T>();
-        if let None = self.components.get(&type_id) {
-            return None;
-        }
-        if let Some(storage) = self.components.get(&type_id) {
-            if let Some(storage) = storage.as_any().downcast_ref::<ComponentStorage<T>>() {
-                for wrapper in storage.data.iter() {
-                    return Some(Entity {
-                        id: wrapper.entity_id,
-                        world: self,
-                    });
-                }
-            }
-        }
-        return None;
+        let wrapper = self.storage::<T>()?.data.first()?;
+        return Some(Entity {
+            id: wrapper.entity_id,
+            world: self,
+        });
     }
 
     // TODO: first with
     fn all_with<T: Component + 'static>(&self) -> Box<dyn Iterator<Item = Entity<'_>> + '_> {
-        let type_id = TypeId::of::<T>();
-        match self.components.get(&type_id) {
+        match self.storage::<T>() {
             Some(storage) => {
-                let storage = storage
-                    .as_any()
-                    .downcast_ref::<ComponentStorage<T>>()
-                    .unwrap();
                 return Box::new(storage.data.iter().map(|a| Entity {
                     id: a.entity_id,
                     world: self,
@@ -207,13 +486,8 @@ impl WorldOp for World {
     fn find_all<T: Component + 'static>(
         &self,
     ) -> Box<dyn Iterator<Item = &ComponentWrapper<T>> + '_> {
-        let type_id = TypeId::of::<T>();
-        match self.components.get(&type_id) {
+        match self.storage::<T>() {
             Some(storage) => {
-                let storage = storage
-                    .as_any()
-                    .downcast_ref::<ComponentStorage<T>>()
-                    .unwrap();
                 return Box::new(storage.data.iter());
             }
             None => return Box::new(std::iter::empty()),
@@ -254,3 +528,80 @@ impl<'a> EntityMut<'a> {
         return self.world.find_component::<T>(self.id);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Gravity(i32);
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[test]
+    fn stale_handles_find_nothing_once_the_slot_is_reused() {
+        let mut world = World::new();
+        let old = world.add_entity().id;
+        world.entity_mut(old).unwrap().assign(Health(1));
+        world.remove_entity(old);
+
+        let new = world.add_entity().id;
+        world.entity_mut(new).unwrap().assign(Health(2));
+        assert_eq!(new.index, old.index);
+        assert_ne!(new.generation, old.generation);
+        assert!(!world.contains(old));
+        assert!(world.entity(old).is_none());
+        assert!(world.entity_mut(old).is_none());
+        assert!(world.find_component::<Health>(old).is_none());
+        assert_eq!(*world.find_component::<Health>(new).unwrap(), Health(2));
+    }
+
+    #[test]
+    fn removing_a_stale_handle_leaves_the_new_entity_alone() {
+        let mut world = World::new();
+        let old = world.add_entity().id;
+        world.remove_entity(old);
+        let new = world.add_entity().id;
+        world.entity_mut(new).unwrap().assign(Health(2));
+
+        world.remove_entity(old);
+        assert!(world.contains(new));
+        assert_eq!(world.entity_count, 1);
+        assert_eq!(*world.find_component::<Health>(new).unwrap(), Health(2));
+    }
+
+    #[test]
+    fn resources_are_added_replaced_and_removed() {
+        let mut world = World::new();
+        assert!(world.get_resource::<Gravity>().is_none());
+        assert_eq!(world.add_resource(Gravity(1)), None);
+        world.resource_mut::<Gravity>().0 += 1;
+        assert_eq!(world.add_resource(Gravity(5)), Some(Gravity(2)));
+        assert_eq!(*world.resource::<Gravity>(), Gravity(5));
+        assert_eq!(world.remove_resource::<Gravity>(), Some(Gravity(5)));
+        assert_eq!(world.remove_resource::<Gravity>(), None);
+        world.init_resource::<Gravity>();
+        assert_eq!(*world.resource::<Gravity>(), Gravity(0));
+    }
+
+    #[test]
+    fn borrowed_resources_are_not_handed_out_twice() {
+        let mut world = World::new();
+        world.add_resource(Gravity(1));
+        let gravity = world.resource::<Gravity>();
+        assert!(world.get_resource::<Gravity>().is_some());
+        assert!(world.get_resource_mut::<Gravity>().is_none());
+        drop(gravity);
+        assert!(world.get_resource_mut::<Gravity>().is_some());
+    }
+
+    #[test]
+    fn detached_resources_keep_their_value() {
+        let mut world = World::new();
+        world.add_resource(Gravity(3));
+        world.detach_lib_code();
+        assert_eq!(*world.resource::<Gravity>(), Gravity(3));
+    }
+}
@@ -0,0 +1,442 @@
+use std::{
+    any::{type_name, TypeId},
+    cell::{Cell, Ref, RefMut},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use super::{Component, ComponentStorage, EntityId, World};
+
+/**
+ * Exclusive borrow of a component from a query, writing through it marks the component as changed
+ */
+pub struct Mut<'w, T> {
+    value: RefMut<'w, T>,
+    changed: &'w Cell<u32>,
+    tick: u32,
+}
+
+impl<'w, T> Deref for Mut<'w, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<'w, T> DerefMut for Mut<'w, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.changed.set(self.tick);
+        &mut self.value
+    }
+}
+
+/**
+ * One element of a query tuple:
+ *  - `&T` / `&mut T`: the entity must have a T, borrowed shared / exclusively (as a Mut)
+ *  - `Option<&T>` / `Option<&mut T>`: the T if the entity has one
+ *  - `EntityId`: the entity itself
+ */
+pub trait QueryParam {
+    type Item<'w>;
+
+    // Component type and whether it's borrowed mutably, None if no component is borrowed
+    fn access() -> Option<(TypeId, &'static str, bool)>;
+    // Entities that have the required component, None if this parameter doesn't narrow the query
+    fn candidates(world: &World) -> Option<Vec<EntityId>>;
+    // None if the entity doesn't match
+    fn fetch<'w>(world: &'w World, entity: EntityId) -> Option<Self::Item<'w>>;
+}
+
+fn candidates<T: Component + 'static>(world: &World) -> Vec<EntityId> {
+    world
+        .storage::<T>()
+        .map(|storage| storage.data.iter().map(|wrapper| wrapper.entity_id).collect())
+        .unwrap_or_default()
+}
+
+fn fetch_ref<'w, T: Component + 'static>(world: &'w World, entity: EntityId) -> Option<Ref<'w, T>> {
+    let storage: &ComponentStorage<T> = world.storage::<T>()?;
+    let wrapper = &storage.data[*storage.entity_map.get(&entity)?];
+    match wrapper.component.try_borrow() {
+        Ok(component) => Some(component),
+        Err(_) => panic!(
+            "Query can't borrow {} of {:?}, it is already borrowed mutably elsewhere",
+            type_name::<T>(),
+            entity
+        ),
+    }
+}
+
+fn fetch_mut<'w, T: Component + 'static>(world: &'w World, entity: EntityId) -> Option<Mut<'w, T>> {
+    let storage: &ComponentStorage<T> = world.storage::<T>()?;
+    let wrapper = &storage.data[*storage.entity_map.get(&entity)?];
+    match wrapper.component.try_borrow_mut() {
+        Ok(component) => Some(Mut {
+            value: component,
+            changed: &wrapper.changed,
+            tick: world.change_tick(),
+        }),
+        Err(_) => panic!(
+            "Query can't borrow {} of {:?} mutably, it is already borrowed elsewhere",
+            type_name::<T>(),
+            entity
+        ),
+    }
+}
+
+impl<T: Component + 'static> QueryParam for &T {
+    type Item<'w> = Ref<'w, T>;
+
+    fn access() -> Option<(TypeId, &'static str, bool)> {
+        Some((TypeId::of::<T>(), type_name::<T>(), false))
+    }
+    fn candidates(world: &World) -> Option<Vec<EntityId>> {
+        Some(candidates::<T>(world))
+    }
+    fn fetch<'w>(world: &'w World, entity: EntityId) -> Option<Self::Item<'w>> {
+        fetch_ref(world, entity)
+    }
+}
+
+impl<T: Component + 'static> QueryParam for &mut T {
+    type Item<'w> = Mut<'w, T>;
+
+    fn access() -> Option<(TypeId, &'static str, bool)> {
+        Some((TypeId::of::<T>(), type_name::<T>(), true))
+    }
+    fn candidates(world: &World) -> Option<Vec<EntityId>> {
+        Some(candidates::<T>(world))
+    }
+    fn fetch<'w>(world: &'w World, entity: EntityId) -> Option<Self::Item<'w>> {
+        fetch_mut(world, entity)
+    }
+}
+
+impl<T: Component + 'static> QueryParam for Option<&T> {
+    type Item<'w> = Option<Ref<'w, T>>;
+
+    fn access() -> Option<(TypeId, &'static str, bool)> {
+        Some((TypeId::of::<T>(), type_name::<T>(), false))
+    }
+    fn candidates(_world: &World) -> Option<Vec<EntityId>> {
+        None
+    }
+    fn fetch<'w>(world: &'w World, entity: EntityId) -> Option<Self::Item<'w>> {
+        Some(fetch_ref(world, entity))
+    }
+}
+
+impl<T: Component + 'static> QueryParam for Option<&mut T> {
+    type Item<'w> = Option<Mut<'w, T>>;
+
+    fn access() -> Option<(TypeId, &'static str, bool)> {
+        Some((TypeId::of::<T>(), type_name::<T>(), true))
+    }
+    fn candidates(_world: &World) -> Option<Vec<EntityId>> {
+        None
+    }
+    fn fetch<'w>(world: &'w World, entity: EntityId) -> Option<Self::Item<'w>> {
+        Some(fetch_mut(world, entity))
+    }
+}
+
+impl QueryParam for EntityId {
+    type Item<'w> = EntityId;
+
+    fn access() -> Option<(TypeId, &'static str, bool)> {
+        None
+    }
+    fn candidates(_world: &World) -> Option<Vec<EntityId>> {
+        None
+    }
+    fn fetch<'w>(_world: &'w World, entity: EntityId) -> Option<Self::Item<'w>> {
+        Some(entity)
+    }
+}
+
+/**
+ * A tuple of QueryParams, see World::query
+ */
+pub trait Query {
+    type Item<'w>;
+
+    fn accesses() -> Vec<(TypeId, &'static str, bool)>;
+    fn candidates(world: &World) -> Option<Vec<EntityId>>;
+    fn fetch<'w>(world: &'w World, entity: EntityId) -> Option<Self::Item<'w>>;
+}
+
+macro_rules! impl_query {
+    ($($param:ident),+) => {
+        impl<$($param: QueryParam),+> Query for ($($param,)+) {
+            type Item<'w> = ($($param::Item<'w>,)+);
+
+            fn accesses() -> Vec<(TypeId, &'static str, bool)> {
+                [$($param::access()),+].into_iter().flatten().collect()
+            }
+            // The smallest storage of all the required components
+            fn candidates(world: &World) -> Option<Vec<EntityId>> {
+                [$($param::candidates(world)),+]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|candidates| candidates.len())
+            }
+            fn fetch<'w>(world: &'w World, entity: EntityId) -> Option<Self::Item<'w>> {
+                Some(($($param::fetch(world, entity)?,)+))
+            }
+        }
+    };
+}
+
+impl_query!(A);
+impl_query!(A, B);
+impl_query!(A, B, C);
+impl_query!(A, B, C, D);
+impl_query!(A, B, C, D, E);
+impl_query!(A, B, C, D, E, F);
+
+// Entity has a T
+pub struct With<T>(PhantomData<T>);
+// Entity doesn't have a T
+pub struct Without<T>(PhantomData<T>);
+// Entity got its T after `since`
+pub struct Added<T>(PhantomData<T>);
+// Entity's T was added or written to after `since`
+pub struct Changed<T>(PhantomData<T>);
+
+/**
+ * Narrows a query down without borrowing anything, see World::query_filtered.
+ * Tuples of filters must all match.
+ */
+pub trait QueryFilter {
+    fn candidates(world: &World) -> Option<Vec<EntityId>>;
+    fn matches(world: &World, entity: EntityId, since: u32) -> bool;
+}
+
+fn ticks<T: Component + 'static>(world: &World, entity: EntityId) -> Option<(u32, u32)> {
+    let wrapper = world.storage::<T>()?.find_wrapper(entity)?;
+    Some((wrapper.added, wrapper.changed.get()))
+}
+
+impl<T: Component + 'static> QueryFilter for With<T> {
+    fn candidates(world: &World) -> Option<Vec<EntityId>> {
+        Some(candidates::<T>(world))
+    }
+    fn matches(world: &World, entity: EntityId, _since: u32) -> bool {
+        ticks::<T>(world, entity).is_some()
+    }
+}
+
+impl<T: Component + 'static> QueryFilter for Without<T> {
+    fn candidates(_world: &World) -> Option<Vec<EntityId>> {
+        None
+    }
+    fn matches(world: &World, entity: EntityId, _since: u32) -> bool {
+        ticks::<T>(world, entity).is_none()
+    }
+}
+
+impl<T: Component + 'static> QueryFilter for Added<T> {
+    fn candidates(world: &World) -> Option<Vec<EntityId>> {
+        Some(candidates::<T>(world))
+    }
+    fn matches(world: &World, entity: EntityId, since: u32) -> bool {
+        ticks::<T>(world, entity).is_some_and(|(added, _)| added > since)
+    }
+}
+
+impl<T: Component + 'static> QueryFilter for Changed<T> {
+    fn candidates(world: &World) -> Option<Vec<EntityId>> {
+        Some(candidates::<T>(world))
+    }
+    fn matches(world: &World, entity: EntityId, since: u32) -> bool {
+        ticks::<T>(world, entity).is_some_and(|(_, changed)| changed > since)
+    }
+}
+
+impl QueryFilter for () {
+    fn candidates(_world: &World) -> Option<Vec<EntityId>> {
+        None
+    }
+    fn matches(_world: &World, _entity: EntityId, _since: u32) -> bool {
+        true
+    }
+}
+
+macro_rules! impl_query_filter {
+    ($($filter:ident),+) => {
+        impl<$($filter: QueryFilter),+> QueryFilter for ($($filter,)+) {
+            fn candidates(world: &World) -> Option<Vec<EntityId>> {
+                [$($filter::candidates(world)),+]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|candidates| candidates.len())
+            }
+            fn matches(world: &World, entity: EntityId, since: u32) -> bool {
+                $($filter::matches(world, entity, since))&&+
+            }
+        }
+    };
+}
+
+impl_query_filter!(A);
+impl_query_filter!(A, B);
+impl_query_filter!(A, B, C);
+impl_query_filter!(A, B, C, D);
+
+/**
+ * Change tick of a system's previous run, what Added / Changed filters compare against.
+ * Everything added or written after begin() shows up in the next run, the system's own changes included.
+ */
+#[derive(Default, Clone, Copy, Debug)]
+pub struct LastRun(u32);
+
+impl LastRun {
+    // Call at the start of the system, returns the `since` tick for query_filtered
+    pub fn begin(&mut self, world: &World) -> u32 {
+        std::mem::replace(&mut self.0, world.increment_change_tick())
+    }
+}
+
+pub struct QueryIter<'w, Q: Query, F: QueryFilter = ()> {
+    world: &'w World,
+    entities: std::vec::IntoIter<EntityId>,
+    since: u32,
+    query: PhantomData<(Q, F)>,
+}
+
+impl<'w, Q: Query, F: QueryFilter> Iterator for QueryIter<'w, Q, F> {
+    type Item = Q::Item<'w>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for entity in self.entities.by_ref() {
+            if !F::matches(self.world, entity, self.since) {
+                continue;
+            }
+            if let Some(item) = Q::fetch(self.world, entity) {
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+impl World {
+    /**
+     * Every entity that has all the required components of `Q`, e.g.
+     * `world.query::<(EntityId, &mut Position, &Mover, Option<&Collider>)>()`
+     * Walks the smallest storage among the required components.
+     * Panics if the same component is asked for twice and at least once mutably.
+     */
+    pub fn query<Q: Query>(&self) -> QueryIter<'_, Q> {
+        self.query_filtered::<Q, ()>(0)
+    }
+
+    /**
+     * Like query, only entities that also match `F` are visited, e.g.
+     * `world.query_filtered::<(&mut Collider,), (With<Player>, Changed<Position>)>(since)`
+     * `since` is the tick Added / Changed compare against, usually from LastRun::begin.
+     */
+    pub fn query_filtered<Q: Query, F: QueryFilter>(&self, since: u32) -> QueryIter<'_, Q, F> {
+        let accesses = Q::accesses();
+        for (i, (type_id, name, mutable)) in accesses.iter().enumerate() {
+            for (other_type_id, _, other_mutable) in accesses[i + 1..].iter() {
+                if type_id == other_type_id && (*mutable || *other_mutable) {
+                    panic!("Query borrows {} twice and at least once mutably", name);
+                }
+            }
+        }
+        // The smallest storage among the required components and the filters that need one
+        let entities = [Q::candidates(self), F::candidates(self)]
+            .into_iter()
+            .flatten()
+            .min_by_key(|candidates| candidates.len())
+            .expect("Query needs at least one required component (&T, &mut T or a With / Added / Changed filter)");
+        QueryIter {
+            world: self,
+            entities: entities.into_iter(),
+            since,
+            query: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::WorldOp;
+
+    struct Health(u32);
+    impl Component for Health {}
+
+    struct Frozen;
+    impl Component for Frozen {}
+
+    fn spawn(world: &mut World, health: u32, frozen: bool) -> EntityId {
+        let mut entity = world.add_entity();
+        entity.assign(Health(health));
+        if frozen {
+            entity.assign(Frozen);
+        }
+        entity.id
+    }
+
+    fn matching<F: QueryFilter>(world: &World, since: u32) -> Vec<EntityId> {
+        let mut entities: Vec<EntityId> = world.query_filtered::<(EntityId,), F>(since).map(|(entity,)| entity).collect();
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn with_and_without() {
+        let mut world = World::new();
+        let warm = spawn(&mut world, 1, false);
+        let frozen = spawn(&mut world, 2, true);
+        assert_eq!(matching::<With<Frozen>>(&world, 0), vec![frozen]);
+        assert_eq!(matching::<(With<Health>, Without<Frozen>)>(&world, 0), vec![warm]);
+    }
+
+    #[test]
+    fn added_shows_up_in_the_next_run_only() {
+        let mut world = World::new();
+        let mut last_run = LastRun::default();
+        let first = spawn(&mut world, 1, false);
+
+        let since = last_run.begin(&world);
+        assert_eq!(matching::<Added<Health>>(&world, since), vec![first]);
+        let second = spawn(&mut world, 2, false);
+
+        let since = last_run.begin(&world);
+        assert_eq!(matching::<Added<Health>>(&world, since), vec![second]);
+        let since = last_run.begin(&world);
+        assert!(matching::<Added<Health>>(&world, since).is_empty());
+    }
+
+    #[test]
+    fn changed_tracks_writes_not_reads() {
+        let mut world = World::new();
+        let mut last_run = LastRun::default();
+        let warm = spawn(&mut world, 1, false);
+        let frozen = spawn(&mut world, 2, true);
+
+        // Added counts as changed
+        let since = last_run.begin(&world);
+        assert_eq!(matching::<Changed<Health>>(&world, since), vec![warm, frozen]);
+        for (entity, mut health, frozen) in world.query::<(EntityId, &mut Health, Option<&Frozen>)>() {
+            if frozen.is_none() {
+                health.0 += 1;
+            } else {
+                assert_eq!(health.0, 2, "{:?} is only read", entity);
+            }
+        }
+
+        let since = last_run.begin(&world);
+        assert_eq!(matching::<Changed<Health>>(&world, since), vec![warm]);
+        world.mark_changed::<Health>(frozen);
+
+        let since = last_run.begin(&world);
+        assert_eq!(matching::<(Changed<Health>, With<Frozen>)>(&world, since), vec![frozen]);
+        let since = last_run.begin(&world);
+        assert!(matching::<Changed<Health>>(&world, since).is_empty());
+    }
+}
@@ -0,0 +1,275 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_yml::{Mapping, Value};
+use std::collections::BTreeMap;
+
+use super::{Component, EntityId, World, WorldOp};
+
+// A deserialized component waiting to be added to an entity
+pub(crate) type Staged = Box<dyn FnOnce(&mut World, EntityId) -> Result<(), String>>;
+
+pub(crate) struct Registration {
+    pub(crate) name: &'static str,
+    attach: fn(&World),
+    has: fn(&World, EntityId) -> bool,
+    serialize: fn(&World, EntityId) -> Option<Result<Value, String>>,
+    pub(crate) serialize_all: fn(&World) -> Result<Vec<(EntityId, Value)>, String>,
+    pub(crate) deserialize: fn(Value) -> Result<Staged, String>,
+}
+
+/**
+ * Component types that opt in to being looked at by name at runtime: listed per entity,
+ * serialized (snapshots, YAML) and walked field by field (inspectors).
+ * Each one is stored under a stable name (not its TypeId, those change between builds).
+ * Components that aren't registered are invisible to all of the above.
+ */
+#[derive(Default)]
+pub struct ComponentRegistry {
+    pub(crate) registrations: Vec<Registration>,
+}
+
+fn attach_storage<T: Component + 'static>(world: &World) {
+    world.storage::<T>();
+}
+
+fn has_component<T: Component + 'static>(world: &World, entity: EntityId) -> bool {
+    world.find_component::<T>(entity).is_some()
+}
+
+fn serialize_component<T: Component + Serialize + 'static>(
+    world: &World,
+    entity: EntityId,
+) -> Option<Result<Value, String>> {
+    let component = world.find_component::<T>(entity)?;
+    Some(serde_yml::to_value(&*component).map_err(|error| error.to_string()))
+}
+
+fn serialize_all<T: Component + Serialize + 'static>(world: &World) -> Result<Vec<(EntityId, Value)>, String> {
+    world
+        .find_all::<T>()
+        .map(|wrapper| {
+            let value = serde_yml::to_value(&*wrapper.component.borrow()).map_err(|error| error.to_string())?;
+            Ok((wrapper.entity_id, value))
+        })
+        .collect()
+}
+
+fn deserialize_component<T: Component + DeserializeOwned + 'static>(value: Value) -> Result<Staged, String> {
+    let component: T = serde_yml::from_value(value).map_err(|error| error.to_string())?;
+    Ok(Box::new(move |world: &mut World, entity: EntityId| {
+        let mut entity = world
+            .entity_mut(entity)
+            .ok_or(format!("Entity {:?} is not in the world", entity))?;
+        entity.assign(component);
+        Ok(())
+    }))
+}
+
+// Calls `visitor` for every leaf of `value`, nested fields are joined with '.' ("speed.x", "!Rect.rect.w")
+fn visit_value(path: &str, value: &Value, visitor: &mut dyn FnMut(&str, &Value)) {
+    let join = |segment: &str| {
+        if path.is_empty() {
+            segment.to_string()
+        } else {
+            format!("{}.{}", path, segment)
+        }
+    };
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping.iter() {
+                let key = match key {
+                    Value::String(key) => key.clone(),
+                    key => serde_yml::to_string(key).unwrap_or_default().trim().to_string(),
+                };
+                visit_value(&join(&key), value, visitor);
+            }
+        }
+        Value::Sequence(sequence) => {
+            for (index, value) in sequence.iter().enumerate() {
+                visit_value(&join(&index.to_string()), value, visitor);
+            }
+        }
+        Value::Tagged(tagged) => visit_value(&join(&tagged.tag.to_string()), &tagged.value, visitor),
+        value => visitor(path, value),
+    }
+}
+
+// The field at `path` (as given to visitors), None if there is no such field
+fn field_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    if path.is_empty() {
+        return Some(value);
+    }
+    let (segment, rest) = path.split_once('.').unwrap_or((path, ""));
+    let field = match value {
+        Value::Mapping(mapping) => mapping.get_mut(segment)?,
+        Value::Sequence(sequence) => sequence.get_mut(segment.parse::<usize>().ok()?)?,
+        Value::Tagged(tagged) if tagged.tag == segment => &mut tagged.value,
+        _ => return None,
+    };
+    field_mut(field, rest)
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T: Component + Serialize + DeserializeOwned + 'static>(&mut self, name: &'static str) -> &mut Self {
+        assert!(
+            self.registrations.iter().all(|registration| registration.name != name),
+            "Component {} registered twice",
+            name
+        );
+        self.registrations.push(Registration {
+            name,
+            attach: attach_storage::<T>,
+            has: has_component::<T>,
+            serialize: serialize_component::<T>,
+            serialize_all: serialize_all::<T>,
+            deserialize: deserialize_component::<T>,
+        });
+        self
+    }
+
+    pub(crate) fn registration(&self, name: &str) -> Result<&Registration, String> {
+        self.registrations
+            .iter()
+            .find(|registration| registration.name == name)
+            .ok_or(format!("Unknown component {}", name))
+    }
+
+    // Attaches this lib's code to the storages of every registered type, see World::detach_lib_code
+    pub fn attach(&self, world: &World) {
+        for registration in self.registrations.iter() {
+            (registration.attach)(world);
+        }
+    }
+
+    // Every registered name, in registration order
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.registrations.iter().map(|registration| registration.name)
+    }
+
+    // Names of the registered components `entity` has
+    pub fn component_names(&self, world: &World, entity: EntityId) -> Vec<&'static str> {
+        self.registrations
+            .iter()
+            .filter(|registration| (registration.has)(world, entity))
+            .map(|registration| registration.name)
+            .collect()
+    }
+
+    pub fn serialize(&self, world: &World, entity: EntityId, name: &str) -> Result<Value, String> {
+        (self.registration(name)?.serialize)(world, entity)
+            .ok_or(format!("Entity {:?} has no {}", entity, name))?
+    }
+
+    // Adds the component to `entity`, replacing the one it has
+    pub fn deserialize(&self, world: &mut World, entity: EntityId, name: &str, value: Value) -> Result<(), String> {
+        let staged = (self.registration(name)?.deserialize)(value).map_err(|error| format!("{}: {}", name, error))?;
+        staged(world, entity).map_err(|error| format!("{}: {}", name, error))
+    }
+
+    /**
+     * Walks the fields of the entity's `name` component, see visit_value for the paths.
+     * The values are serialized copies, write them back through set_field.
+     */
+    pub fn visit_fields(
+        &self,
+        world: &World,
+        entity: EntityId,
+        name: &str,
+        visitor: &mut dyn FnMut(&str, &Value),
+    ) -> Result<(), String> {
+        let value = self.serialize(world, entity, name)?;
+        visit_value("", &value, visitor);
+        Ok(())
+    }
+
+    // Changes one field, the component goes through a serialize / deserialize round trip
+    pub fn set_field(&self, world: &mut World, entity: EntityId, name: &str, path: &str, field: Value) -> Result<(), String> {
+        let mut value = self.serialize(world, entity, name)?;
+        *field_mut(&mut value, path).ok_or(format!("{} has no field {}", name, path))? = field;
+        self.deserialize(world, entity, name, value)
+    }
+
+    // Every registered component of `entity` as a YAML mapping of name -> component
+    pub fn entity_to_yaml(&self, world: &World, entity: EntityId) -> Result<String, String> {
+        if !world.contains(entity) {
+            return Err(format!("Entity {:?} is not in the world", entity));
+        }
+        let mut components = BTreeMap::new();
+        for name in self.component_names(world, entity) {
+            components.insert(name, self.serialize(world, entity, name)?);
+        }
+        serde_yml::to_string(&components).map_err(|error| error.to_string())
+    }
+
+    /**
+     * Adds the components in `yaml` (as written by entity_to_yaml) to `entity`.
+     * Fails before touching the entity if any of them isn't registered or doesn't deserialize.
+     */
+    pub fn entity_from_yaml(&self, world: &mut World, entity: EntityId, yaml: &str) -> Result<(), String> {
+        if !world.contains(entity) {
+            return Err(format!("Entity {:?} is not in the world", entity));
+        }
+        let components: Mapping = serde_yml::from_str(yaml).map_err(|error| error.to_string())?;
+        let mut staged = Vec::with_capacity(components.len());
+        for (name, value) in components {
+            let name = name.as_str().ok_or(format!("Component name {:?} is not a string", name))?;
+            let registration = self.registration(name)?;
+            let component = (registration.deserialize)(value).map_err(|error| format!("{}: {}", name, error))?;
+            staged.push((registration.name, component));
+        }
+        for (name, component) in staged {
+            component(world, entity).map_err(|error| format!("{}: {}", name, error))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Speed {
+        x: f32,
+        y: f32,
+    }
+    impl Component for Speed {}
+
+    fn registry() -> ComponentRegistry {
+        let mut registry = ComponentRegistry::new();
+        registry.register::<Health>("health").register::<Speed>("speed");
+        registry
+    }
+
+    #[test]
+    fn entity_yaml_round_trip() {
+        let mut world = World::new();
+        let entity = world.add_entity().id;
+        world.entity_mut(entity).unwrap().assign(Health(3));
+        world.entity_mut(entity).unwrap().assign(Speed { x: 1.5, y: -2.0 });
+        let yaml = registry().entity_to_yaml(&world, entity).unwrap();
+
+        let copy = world.add_entity().id;
+        registry().entity_from_yaml(&mut world, copy, &yaml).unwrap();
+        assert_eq!(*world.find_component::<Health>(copy).unwrap(), Health(3));
+        assert_eq!(*world.find_component::<Speed>(copy).unwrap(), Speed { x: 1.5, y: -2.0 });
+        assert_eq!(registry().entity_to_yaml(&world, copy).unwrap(), yaml);
+    }
+
+    #[test]
+    fn bad_yaml_leaves_the_entity_alone() {
+        let mut world = World::new();
+        let entity = world.add_entity().id;
+        let yaml = "health: 3\nspeed: fast\n";
+        assert!(registry().entity_from_yaml(&mut world, entity, yaml).is_err());
+        assert!(registry().component_names(&world, entity).is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+use super::World;
+
+// Stages run in this order, a stage is only run when asked for (Schedule::run)
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Stage {
+    PreUpdate,
+    Update,
+    PostUpdate,
+    Render,
+}
+
+pub const STAGES: [Stage; 4] = [Stage::PreUpdate, Stage::Update, Stage::PostUpdate, Stage::Render];
+
+/**
+ * Something the Schedule runs. `C` is whatever the systems need besides the World
+ * (the game state, which owns the World). Closures taking `&mut C` are systems too.
+ */
+pub trait System<C> {
+    fn run(&mut self, context: &mut C);
+}
+
+impl<C, F: FnMut(&mut C)> System<C> for F {
+    fn run(&mut self, context: &mut C) {
+        self(context)
+    }
+}
+
+type Condition<C> = Box<dyn Fn(&C) -> bool>;
+
+struct ScheduledSystem<C> {
+    name: String,
+    stage: Stage,
+    system: Box<dyn System<C>>,
+    conditions: Vec<Condition<C>>,
+    before: Vec<String>,
+    after: Vec<String>,
+    enabled: bool,
+}
+
+/**
+ * Systems grouped in stages. Inside a stage systems run in the order they were added
+ * unless before / after say otherwise. Commands are applied at the end of every stage.
+ */
+pub struct Schedule<C> {
+    systems: Vec<ScheduledSystem<C>>,
+    sorted: bool,
+}
+
+impl<C> Default for Schedule<C> {
+    fn default() -> Self {
+        Schedule {
+            systems: Vec::new(),
+            sorted: true,
+        }
+    }
+}
+
+// Returned by Schedule::add to set ordering and run conditions
+pub struct SystemConfig<'a, C> {
+    system: &'a mut ScheduledSystem<C>,
+}
+
+impl<'a, C> SystemConfig<'a, C> {
+    pub fn before(self, name: &str) -> Self {
+        self.system.before.push(name.to_string());
+        self
+    }
+
+    pub fn after(self, name: &str) -> Self {
+        self.system.after.push(name.to_string());
+        self
+    }
+
+    // The system is skipped on runs where any of its conditions is false
+    pub fn run_if(self, condition: impl Fn(&C) -> bool + 'static) -> Self {
+        self.system.conditions.push(Box::new(condition));
+        self
+    }
+}
+
+impl<C: AsMut<World>> Schedule<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, stage: Stage, name: &str, system: impl System<C> + 'static) -> SystemConfig<'_, C> {
+        assert!(
+            self.systems.iter().all(|system| system.name != name),
+            "System {} added twice",
+            name
+        );
+        self.sorted = false;
+        self.systems.push(ScheduledSystem {
+            name: name.to_string(),
+            stage,
+            system: Box::new(system),
+            conditions: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+            enabled: true,
+        });
+        SystemConfig {
+            system: self.systems.last_mut().unwrap(),
+        }
+    }
+
+    pub fn run(&mut self, stage: Stage, context: &mut C) {
+        if !self.sorted {
+            self.sort();
+        }
+        for system in self.systems.iter_mut().filter(|system| system.stage == stage) {
+            if system.enabled && system.conditions.iter().all(|condition| condition(context)) {
+                system.system.run(context);
+            }
+        }
+        context.as_mut().apply_commands();
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        if let Some(system) = self.systems.iter_mut().find(|system| system.name == name) {
+            system.enabled = enabled;
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.systems.iter().any(|system| system.name == name && system.enabled)
+    }
+
+    // (name, stage, enabled) in run order
+    pub fn systems(&mut self) -> impl Iterator<Item = (&str, Stage, bool)> {
+        if !self.sorted {
+            self.sort();
+        }
+        self.systems
+            .iter()
+            .map(|system| (system.name.as_str(), system.stage, system.enabled))
+    }
+
+    // Carries the enable toggles over from a previous schedule (rebuilt after a hot reload)
+    pub fn copy_enabled(&mut self, other: &Schedule<C>) {
+        for system in self.systems.iter_mut() {
+            if let Some(old) = other.systems.iter().find(|old| old.name == system.name) {
+                system.enabled = old.enabled;
+            }
+        }
+    }
+
+    /**
+     * Orders the systems by stage, then by before / after constraints, insertion order otherwise.
+     * Panics on constraints naming unknown systems or systems of another stage, and on cycles.
+     */
+    fn sort(&mut self) {
+        let count = self.systems.len();
+        // edges[a] holds the systems that have to run after a
+        let mut edges: Vec<Vec<usize>> = vec![Vec::new(); count];
+        let index_of = |name: &str, from: &ScheduledSystem<C>| {
+            let index = self
+                .systems
+                .iter()
+                .position(|system| system.name == name)
+                .unwrap_or_else(|| panic!("System {} is ordered against unknown system {}", from.name, name));
+            assert!(
+                self.systems[index].stage == from.stage,
+                "System {} is ordered against {}, which runs in another stage",
+                from.name,
+                name
+            );
+            index
+        };
+        for (index, system) in self.systems.iter().enumerate() {
+            for name in system.before.iter() {
+                edges[index].push(index_of(name, system));
+            }
+            for name in system.after.iter() {
+                edges[index_of(name, system)].push(index);
+            }
+        }
+
+        let mut incoming = vec![0; count];
+        for targets in edges.iter() {
+            for target in targets {
+                incoming[*target] += 1;
+            }
+        }
+        let mut order = Vec::with_capacity(count);
+        let mut done = vec![false; count];
+        while order.len() < count {
+            // Earliest stage first, then the earliest added system that has nothing left to wait for
+            let next = (0..count)
+                .filter(|index| !done[*index] && incoming[*index] == 0)
+                .min_by_key(|index| (self.systems[*index].stage, *index));
+            let Some(next) = next else {
+                let stuck: Vec<&str> = (0..count)
+                    .filter(|index| !done[*index])
+                    .map(|index| self.systems[index].name.as_str())
+                    .collect();
+                panic!("System ordering has a cycle between {:?}", stuck);
+            };
+            done[next] = true;
+            for target in edges[next].iter() {
+                incoming[*target] -= 1;
+            }
+            order.push(next);
+        }
+
+        let mut systems: Vec<Option<ScheduledSystem<C>>> = self.systems.drain(..).map(Some).collect();
+        self.systems = order.into_iter().map(|index| systems[index].take().unwrap()).collect();
+        self.sorted = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Context {
+        world: World,
+        ran: Vec<&'static str>,
+        paused: bool,
+    }
+
+    impl AsMut<World> for Context {
+        fn as_mut(&mut self) -> &mut World {
+            &mut self.world
+        }
+    }
+
+    impl Context {
+        fn new() -> Self {
+            Context {
+                world: World::new(),
+                ran: Vec::new(),
+                paused: false,
+            }
+        }
+    }
+
+    fn log(name: &'static str) -> impl FnMut(&mut Context) {
+        move |context: &mut Context| context.ran.push(name)
+    }
+
+    fn run_all(schedule: &mut Schedule<Context>) -> Vec<&'static str> {
+        let mut context = Context::new();
+        for stage in STAGES {
+            schedule.run(stage, &mut context);
+        }
+        context.ran
+    }
+
+    #[test]
+    fn systems_run_by_stage_then_constraints() {
+        let mut schedule = Schedule::new();
+        schedule.add(Stage::Render, "draw", log("draw"));
+        schedule.add(Stage::Update, "move", log("move"));
+        schedule.add(Stage::Update, "collide", log("collide")).before("move");
+        schedule.add(Stage::Update, "animate", log("animate"));
+        schedule.add(Stage::Update, "input", log("input")).before("collide");
+        schedule.add(Stage::PreUpdate, "time", log("time"));
+        // animate has nothing to wait for and was added before input
+        assert_eq!(run_all(&mut schedule), vec!["time", "animate", "input", "collide", "move", "draw"]);
+
+        let order: Vec<&str> = schedule.systems().map(|(name, _, _)| name).collect();
+        assert_eq!(order, vec!["time", "animate", "input", "collide", "move", "draw"]);
+    }
+
+    #[test]
+    fn after_waits_for_systems_added_later() {
+        let mut schedule = Schedule::new();
+        schedule.add(Stage::Update, "camera", log("camera")).after("player");
+        schedule.add(Stage::Update, "player", log("player"));
+        assert_eq!(run_all(&mut schedule), vec!["player", "camera"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cycle")]
+    fn ordering_cycles_panic() {
+        let mut schedule = Schedule::new();
+        schedule.add(Stage::Update, "a", log("a")).before("b");
+        schedule.add(Stage::Update, "b", log("b")).before("c");
+        schedule.add(Stage::Update, "c", log("c")).before("a");
+        run_all(&mut schedule);
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown system")]
+    fn ordering_against_unknown_systems_panics() {
+        let mut schedule = Schedule::new();
+        schedule.add(Stage::Update, "a", log("a")).after("missing");
+        run_all(&mut schedule);
+    }
+
+    #[test]
+    fn run_conditions_skip_systems() {
+        let mut schedule = Schedule::new();
+        schedule.add(Stage::Update, "physics", log("physics")).run_if(|context: &Context| !context.paused);
+        schedule.add(Stage::Update, "ui", log("ui"));
+
+        let mut context = Context::new();
+        schedule.run(Stage::Update, &mut context);
+        context.paused = true;
+        schedule.run(Stage::Update, &mut context);
+        assert_eq!(context.ran, vec!["physics", "ui", "ui"]);
+    }
+
+    #[test]
+    fn disabled_systems_dont_run() {
+        let mut schedule = Schedule::new();
+        schedule.add(Stage::Update, "physics", log("physics"));
+        schedule.add(Stage::Update, "ui", log("ui"));
+        schedule.set_enabled("physics", false);
+        assert!(!schedule.is_enabled("physics"));
+        assert_eq!(run_all(&mut schedule), vec!["ui"]);
+
+        schedule.set_enabled("physics", true);
+        assert_eq!(run_all(&mut schedule), vec!["physics", "ui"]);
+    }
+
+    #[test]
+    fn enable_toggles_survive_a_rebuild() {
+        let build = || {
+            let mut schedule = Schedule::new();
+            schedule.add(Stage::Update, "physics", log("physics"));
+            schedule.add(Stage::Update, "ui", log("ui"));
+            schedule
+        };
+        let mut old = build();
+        old.set_enabled("ui", false);
+
+        let mut rebuilt = build();
+        rebuilt.copy_enabled(&old);
+        assert!(rebuilt.is_enabled("physics"));
+        assert!(!rebuilt.is_enabled("ui"));
+        assert_eq!(run_all(&mut rebuilt), vec!["physics"]);
+    }
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use serde_yml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{ComponentRegistry, EntityId, World};
+
+/**
+ * Serialized copy of a World: every entity id and, for each registered component type,
+ * the components keyed by the entity that owns them.
+ */
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct WorldSnapshot {
+    pub entities: Vec<EntityId>,
+    pub components: BTreeMap<String, Vec<(EntityId, Value)>>,
+}
+
+// Components that aren't registered are left out of snapshots
+impl ComponentRegistry {
+    pub fn save(&self, world: &World) -> Result<WorldSnapshot, String> {
+        let mut snapshot = WorldSnapshot {
+            entities: world.entity_ids().collect(),
+            components: BTreeMap::new(),
+        };
+        for registration in self.registrations.iter() {
+            let components =
+                (registration.serialize_all)(world).map_err(|error| format!("{}: {}", registration.name, error))?;
+            snapshot.components.insert(registration.name.to_string(), components);
+        }
+        Ok(snapshot)
+    }
+
+    /**
+     * Replaces everything in `world` with the snapshot contents.
+     * Every component is deserialized before the world is touched, a bad snapshot leaves it as it was.
+     */
+    pub fn restore(&self, world: &mut World, snapshot: WorldSnapshot) -> Result<(), String> {
+        let entities: BTreeSet<EntityId> = snapshot.entities.iter().copied().collect();
+        // One slot per index, two generations of the same entity can't both be alive
+        let mut indices = BTreeSet::new();
+        for entity in entities.iter() {
+            if !indices.insert(entity.index) {
+                return Err(format!("Entity index {} is in the snapshot twice", entity.index));
+            }
+        }
+        let mut staged = Vec::new();
+        for (name, components) in snapshot.components {
+            let registration = self.registration(&name).map_err(|error| format!("{} in snapshot", error))?;
+            for (entity, value) in components {
+                if !entities.contains(&entity) {
+                    return Err(format!("{}: entity {:?} is not in the snapshot", name, entity));
+                }
+                let component = (registration.deserialize)(value).map_err(|error| format!("{}: {}", name, error))?;
+                staged.push((registration.name, entity, component));
+            }
+        }
+
+        world.clear();
+        for entity in entities {
+            world.add_entity_with_id(entity);
+        }
+        for (name, entity, component) in staged {
+            component(world, entity).map_err(|error| format!("{}: {}", name, error))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{Component, WorldOp};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Health(u32);
+    impl Component for Health {}
+
+    fn registry() -> ComponentRegistry {
+        let mut registry = ComponentRegistry::new();
+        registry.register::<Health>("health");
+        registry
+    }
+
+    #[test]
+    fn save_and_restore() {
+        let mut world = World::new();
+        let entity = world.add_entity().id;
+        world.entity_mut(entity).unwrap().assign(Health(3));
+        let snapshot = registry().save(&world).unwrap();
+
+        let mut restored = World::new();
+        registry().restore(&mut restored, snapshot).unwrap();
+        assert_eq!(*restored.find_component::<Health>(entity).unwrap(), Health(3));
+    }
+
+    #[test]
+    fn bad_snapshot_leaves_the_world_alone() {
+        let mut world = World::new();
+        let entity = world.add_entity().id;
+        world.entity_mut(entity).unwrap().assign(Health(3));
+        let mut snapshot = registry().save(&world).unwrap();
+        snapshot.components.get_mut("health").unwrap()[0].1 = Value::String("full".to_string());
+
+        assert!(registry().restore(&mut world, snapshot).is_err());
+        assert_eq!(*world.find_component::<Health>(entity).unwrap(), Health(3));
+    }
+
+    #[test]
+    fn duplicate_indices_leave_the_world_alone() {
+        let mut world = World::new();
+        let entity = world.add_entity().id;
+        world.entity_mut(entity).unwrap().assign(Health(3));
+        let mut snapshot = registry().save(&world).unwrap();
+        snapshot.entities.push(EntityId {
+            index: entity.index,
+            generation: entity.generation + 1,
+        });
+
+        assert!(registry().restore(&mut world, snapshot).is_err());
+        assert!(world.contains(entity));
+        assert_eq!(*world.find_component::<Health>(entity).unwrap(), Health(3));
+    }
+}
@@ -369,7 +369,7 @@ impl Batch {
     pub fn circle_fan(
         &mut self,
         center: (f32, f32),
-        points: &Vec<(f32, f32)>,
+        points: &[(f32, f32)],
         color: (f32, f32, f32, f32),
     ) {
         // TODO: use fan instead of triangles
@@ -1,7 +1,8 @@
-use std::{arch::aarch64::vabds_f32, ops};
+use std::ops;
 
 use gl::SET;
 use sdl2::rect::Point;
+use serde::{Deserialize, Serialize};
 
 pub struct Color {
     pub r: u8,
@@ -45,7 +46,7 @@ impl From<PointF> for (f32, f32) {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RectF {
     pub x: f32,
     pub y: f32,
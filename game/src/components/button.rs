@@ -2,10 +2,13 @@ use engine::{
     ecs::{Component, World, WorldOp},
 };
 
-use crate::content::{self, Content};
+use serde::{Deserialize, Serialize};
+
+use crate::events::ButtonPressed;
 
 use super::{collider::Collider, sprite::Sprite};
 
+#[derive(Serialize, Deserialize)]
 pub struct Button {
     pub name: String,
     pub pressed: bool,
@@ -28,13 +31,18 @@ impl Button {
             let mut button = button_entity.get::<Button>();
             let button_collider = button_entity.get::<Collider>();
             let mut button_sprite = button_entity.get::<Sprite>();
-            button.pressed = !button_collider.collisions.is_empty();
+            let pressed = !button_collider.collisions.is_empty();
+            if pressed && !button.pressed {
+                world.send_event(ButtonPressed {
+                    entity: button_entity.id,
+                    name: button.name.clone(),
+                });
+            }
+            button.pressed = pressed;
             if button.pressed {
-                let s = &Content::sprite(&String::from("ButtonPressed"));
-                button_sprite.update_animation(s);
+                button_sprite.update_animation("ButtonPressed");
             } else {
-                let s = &Content::sprite(&String::from("Button"));
-                button_sprite.update_animation(s);
+                button_sprite.update_animation("Button");
             }
         }
     }
@@ -1,14 +1,16 @@
 use engine::{
-    ecs::{Component, World, WorldOp},
+    ecs::{Component, EntityId, World, WorldOp},
     graphics::{
         batch::Batch,
         common::{PointF, RectF},
     },
 };
 
+use serde::{Deserialize, Serialize};
+
 use crate::Position;
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ColliderType {
     Circle {
         radius: f32,
@@ -24,19 +26,19 @@ pub enum ColliderType {
     },
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Direction {
     HORIZONTAL,
     VERTICAL,
 }
 #[allow(dead_code)]
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Collision {
-    pub other: u32,
+    pub other: EntityId,
     pub directions: Direction,
     pub self_velocity: glm::Vec2,
 }
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Collider {
     pub collider_type: ColliderType,
     pub collisions: Vec<Collision>,
@@ -83,7 +85,7 @@ impl Collider {
 
     pub fn check_all(
         &self,
-        self_id: u32,
+        self_id: EntityId,
         self_position: &Position,
         offset: PointF,
         world: &World,
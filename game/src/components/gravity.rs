@@ -1,6 +1,7 @@
 use engine::ecs::Component;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Gravity {
     pub value: f32,
 }
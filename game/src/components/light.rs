@@ -1,69 +1,54 @@
 use engine::ecs::{Component, World, WorldOp};
+use serde::{Deserialize, Serialize};
 
 use super::button::Button;
 
-pub struct Light {
-    pub offset_x: f32,
-    pub offset_y: f32,
-}
+// Lit around the entity's Position, attach it to a parent (Transform) to offset it
+#[derive(Serialize, Deserialize)]
+pub struct Light;
 
 impl Light {
-    pub fn with_offset(x: f32, y: f32) -> Self {
-        return Light {
-            offset_x: x,
-            offset_y: y,
-        };
-    }
     pub fn new() -> Self {
-        Light {
-            offset_x: 0f32,
-            offset_y: 0f32,
-        }
+        Light
     }
 }
 impl Component for Light {}
 
+#[derive(Serialize, Deserialize)]
 pub struct LightSwitch {
-    pub button_name: &'static str,
+    pub button_name: String,
     turned_on: bool,
     old_button_state: bool,
 }
 impl Component for LightSwitch {}
 
 impl LightSwitch {
-    pub fn new(button_name: &'static str) -> Self {
+    pub fn new(button_name: &str) -> Self {
         LightSwitch {
-            button_name,
+            button_name: button_name.to_string(),
             turned_on: false,
             old_button_state: false,
         }
     }
-    pub fn update(world: &mut World) {
-        let mut turn_on: Vec<u32> = Vec::new();
-        let mut turn_off: Vec<u32> = Vec::new();
+    // Lights are added / removed through commands, applied at the end of the tick
+    pub fn update(world: &World) {
+        let commands = world.commands();
         for light_switch_entity in world.all_with::<LightSwitch>() {
             let mut ls = light_switch_entity.get::<LightSwitch>();
-            let is_pressed = Button::is_pressed(world, ls.button_name);
+            let is_pressed = Button::is_pressed(world, &ls.button_name);
             if is_pressed && !ls.old_button_state {
                 ls.turned_on = !ls.turned_on;
             }
             ls.old_button_state = is_pressed;
             if ls.turned_on {
                 if let None = light_switch_entity.has::<Light>() {
-                    turn_on.push(light_switch_entity.id);
+                    commands.insert(light_switch_entity.id, Light::new());
                 }
             } else {
                 if light_switch_entity.has::<Light>().is_some() {
-                    turn_off.push(light_switch_entity.id);
+                    commands.remove::<Light>(light_switch_entity.id);
                 }
             }
         }
-
-        turn_on
-            .iter()
-            .for_each(|id| world.entity_mut(*id).assign(Light::new()));
-        turn_off.iter().for_each(|id| {
-            world.entity_mut(*id).unassign::<Light>();
-        })
     }
 }
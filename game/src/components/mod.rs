@@ -11,6 +11,51 @@ pub mod sprite;
 pub mod button;
 use std::cmp::PartialOrd;
 use std::ops::{Add, Sub};
+use std::sync::OnceLock;
+
+use engine::ecs::{Children, ComponentRegistry, GlobalTransform, Parent, Transform};
+
+use self::{
+    button::Button,
+    collider::Collider,
+    gravity::Gravity,
+    light::{Light, LightSwitch},
+    mover::Mover,
+    player::Player,
+    position::Position,
+    rope::{Link, PointMass, Rope},
+    sprite::Sprite,
+};
+
+/**
+ * Components known by name at runtime (save states, the inspector), new components need an entry here to survive a load.
+ * Built once per loaded lib: the registrations point at this lib's code, so they can't live in the GameState.
+ */
+pub fn registry() -> &'static ComponentRegistry {
+    static REGISTRY: OnceLock<ComponentRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut registry = ComponentRegistry::new();
+        registry
+            .register::<Position>("position")
+            .register::<Mover>("mover")
+            .register::<Gravity>("gravity")
+            .register::<Player>("player")
+            .register::<Collider>("collider")
+            .register::<Sprite>("sprite")
+            .register::<Light>("light")
+            .register::<LightSwitch>("light_switch")
+            .register::<Button>("button")
+            .register::<PointMass>("point_mass")
+            .register::<Link>("link")
+            .register::<Rope>("rope")
+            .register::<Parent>("parent")
+            .register::<Children>("children")
+            .register::<Transform>("transform")
+            .register::<GlobalTransform>("global_transform");
+        registry
+    })
+}
+
 
 // Define a trait with min and max methods
 pub trait MinMax {
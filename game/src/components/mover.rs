@@ -1,12 +1,25 @@
 use engine::ecs::Component;
+use serde::{Deserialize, Serialize};
 
-#[derive(Default, Clone)]
+use super::position::Position;
+
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Mover {
     pub speed: glm::Vec2,
     pub reminder: glm::Vec2,
+    // Position before the last tick, used to interpolate rendering between ticks
+    pub previous: Option<glm::Vec2>,
 }
 
-impl Mover {}
+impl Mover {
+    // Where to draw an entity at `position`, between its last two simulated positions
+    pub fn interpolate(&self, position: &Position) -> glm::Vec2 {
+        match self.previous {
+            Some(previous) => glm::lerp(&previous, &position.as_vec2(), crate::time().alpha),
+            None => position.as_vec2(),
+        }
+    }
+}
 
 impl Component for Mover {
     const CAPACITY: usize = 64;
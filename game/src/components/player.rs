@@ -1,6 +1,7 @@
 use engine::ecs::component::Component;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Player {
     pub in_air: bool,
     pub was_in_air: bool,
@@ -1,6 +1,7 @@
 use engine::ecs::component::Component;
+use serde::{Deserialize, Serialize};
 
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -225,7 +225,9 @@ pub struct Room {
     pub rect: RectF,
     // This is essentially the camera in world space, move out of here?
     pub camera_ortho: glm::Mat4,
-    pub is_dirty: bool, // does it need to be re-render in the maps_color target
+    // Does it need to be re-rendered into the map targets. Rooms are map content (Content::map), not
+    // components: they outlive world.clear() (scene changes, snapshot restores), so Changed<T> can't track them
+    pub is_dirty: bool,
 }
 
 impl Room {
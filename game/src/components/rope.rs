@@ -1,40 +1,42 @@
 #![allow(dead_code)]
 use engine::{
-    ecs::{Component, World, WorldOp},
+    ecs::{Component, EntityId, World, WorldOp},
     graphics::common::RectF,
 };
 
-use crate::{
-    components::collider::{Collider, ColliderType},
-    content::{self, Content},
-};
+use serde::{Deserialize, Serialize};
+
+use crate::components::collider::{Collider, ColliderType};
 
 use super::{gravity::Gravity, mover::Mover, position::Position, sprite::Sprite};
 
 // Linked list?
 #[allow(dead_code)]
+#[derive(Serialize, Deserialize)]
 pub struct PointMass {
-    pub id: u32,
+    pub id: EntityId,
     pub mass: f32,
     pub position: glm::Vec2,
     pub old_position: glm::Vec2,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Link {
-    pub to: u32,
+    pub to: EntityId,
 }
 impl Component for Link {}
 
 impl PointMass {
-    pub fn new(gravity: f32, mass: f32, position: glm::Vec2, world: &mut World, prev: u32) -> u32 {
+    pub fn new(gravity: f32, mass: f32, position: glm::Vec2, world: &mut World, prev: Option<EntityId>) -> EntityId {
         let mut entity = world.add_entity();
         let id = entity.id;
         entity.assign(Position::new(position.x as i32, position.y as i32));
-        entity.assign(Sprite::new(&Content::sprite("rope")));
+        entity.assign(Sprite::new("rope"));
         entity.assign(Gravity { value: gravity });
         entity.assign(Mover {
             speed: glm::vec2(0.2, 0.0),
             reminder: glm::vec2(0.0, 0.0),
+            previous: None,
         });
         entity.assign(Collider::new(
             ColliderType::Rect {
@@ -47,7 +49,7 @@ impl PointMass {
             },
             true,
         ));
-        if prev != 0 {
+        if let Some(prev) = prev {
             entity.assign(Link { to: prev });
         }
         let point = Self {
@@ -63,14 +65,15 @@ impl PointMass {
 impl Component for PointMass {}
 
 #[allow(dead_code)]
+#[derive(Serialize, Deserialize)]
 pub struct Rope {
-    pub points: Vec<u32>,
+    pub points: Vec<EntityId>,
 }
 impl Rope {
     pub fn new() -> Self {
         Self { points: Vec::new() }
     }
-    pub fn add_point(&mut self, point: u32) {
+    pub fn add_point(&mut self, point: EntityId) {
         self.points.push(point);
     }
 }
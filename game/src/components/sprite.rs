@@ -3,10 +3,10 @@ use engine::{
     graphics::texture::{SubTexture, Texture},
 };
 use ldtk_rust::TilesetDefinition;
-use serde::de;
+use serde::{de, Deserialize, Serialize};
 use std::{collections::HashMap, rc::Rc};
 
-use crate::components::room::Tile;
+use crate::{components::room::Tile, content::Content};
 
 #[derive(Debug)]
 pub struct Frame {
@@ -65,7 +65,10 @@ pub struct Animation {
 }
 
 #[allow(dead_code)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "SavedSprite", try_from = "SavedSprite")]
 pub struct Sprite {
+    name: String, // Content::sprite() name, animations are saved by name too
     current_frame: usize,
     frame_counter: u32,
     animations: &'static HashMap<String, Animation>,
@@ -80,6 +83,66 @@ pub struct Sprite {
 
 impl Component for Sprite {}
 
+// What a Sprite looks like in a save file, its animations live in Content and can't be serialized
+#[derive(Serialize, Deserialize)]
+struct SavedSprite {
+    sprite: String,
+    animation: String,
+    next_animation: String,
+    current_frame: usize,
+    frame_counter: u32,
+    scale_x: f32,
+    scale_y: f32,
+    flip_x: bool,
+    flip_y: bool,
+    playing: bool,
+}
+
+impl From<Sprite> for SavedSprite {
+    fn from(sprite: Sprite) -> Self {
+        SavedSprite {
+            sprite: sprite.name,
+            animation: sprite.current_animation.name.clone(),
+            next_animation: sprite.next_animation.name.clone(),
+            current_frame: sprite.current_frame,
+            frame_counter: sprite.frame_counter,
+            scale_x: sprite.scale_x,
+            scale_y: sprite.scale_y,
+            flip_x: sprite.flip_x,
+            flip_y: sprite.flip_y,
+            playing: sprite.playing,
+        }
+    }
+}
+
+impl TryFrom<SavedSprite> for Sprite {
+    type Error = String;
+
+    fn try_from(saved: SavedSprite) -> Result<Self, Self::Error> {
+        let animations = Content::find_sprite(&saved.sprite).ok_or(format!("Unknown sprite {}", saved.sprite))?;
+        let animation = |name: &str| {
+            animations
+                .get(name)
+                .ok_or(format!("Sprite {} has no animation {}", saved.sprite, name))
+        };
+        let current_animation = animation(&saved.animation)?;
+        let next_animation = animation(&saved.next_animation)?;
+        Ok(Sprite {
+            current_frame: saved.current_frame.min(current_animation.frames.len().saturating_sub(1)),
+            frame_counter: saved.frame_counter,
+            animations,
+            current_animation,
+            next_animation,
+            scale_x: saved.scale_x,
+            scale_y: saved.scale_y,
+            flip_x: saved.flip_x,
+            flip_y: saved.flip_y,
+            playing: saved.playing,
+            name: saved.sprite,
+        })
+    }
+}
+
 #[allow(dead_code)]
 impl Sprite {
     pub fn stop(&mut self) {
@@ -109,8 +172,10 @@ impl Sprite {
             .expect("Missing frame");
         &frame.image
     }
-    pub fn update_animation(&mut self, animations: &'static HashMap<String, Animation>) {
+    pub fn update_animation(&mut self, name: &str) {
+        let animations = Content::sprite(name);
         let first_key = animations.keys().next().expect("No animations found");
+        self.name = name.to_string();
         self.animations = animations;
         self.current_animation = animations.get(first_key).unwrap();
         self.next_animation = animations.get(first_key).unwrap();
@@ -138,9 +203,11 @@ impl Sprite {
             self.current_frame = (self.current_frame + 1) % self.current_animation.frames.len();
         }
     }
-    pub fn new(animations: &'static HashMap<String, Animation>) -> Sprite {
+    pub fn new(name: &str) -> Sprite {
+        let animations = Content::sprite(name);
         let first_key = animations.keys().next().expect("No animations found");
         Sprite {
+            name: name.to_string(),
             animations,
             current_animation: animations.get(first_key).unwrap(),
             next_animation: animations.get(first_key).unwrap(),
@@ -10,13 +10,11 @@ use engine::{
     },
 };
 use ldtk_rust::Project;
-use std::mem::size_of;
 
 use crate::components::{room::{MapData, Room, SavedRoom}, sprite::Frame};
 use crate::{
     components::sprite::{Animation, Tileset},
-    game_state::GameState,
-    MEMORY_PTR,
+    CONTENT_ROOT, MEMORY_PTR,
 };
 use crate::map::Map;
 
@@ -31,12 +29,11 @@ pub struct Content {
 }
 
 impl Content {
+    // Part of reload::STATE_LAYOUT, list new fields here
+    pub const LAYOUT: u64 = common::struct_layout!(Content; tilesets, textures, sprites, tracks, map);
+
     pub fn get() -> &'static mut Content {
-        unsafe {
-            let storage_ptr = (*MEMORY_PTR).storage.as_mut_ptr() as *mut GameState;
-            let content = storage_ptr.add(size_of::<GameState>()) as *mut Content;
-            &mut (*content)
-        }
+        unsafe { &mut *((*MEMORY_PTR).roots[CONTENT_ROOT] as *mut Content) }
     }
 
     pub fn sprite(name: &str) -> &'static HashMap<String, Animation> {
@@ -44,6 +41,11 @@ impl Content {
         &Content::get().sprites[name]
     }
 
+    // Like sprite() but for names that come from outside the code (save files)
+    pub fn find_sprite(name: &str) -> Option<&'static HashMap<String, Animation>> {
+        Content::get().sprites.get(name)
+    }
+
     pub fn load(content_ptr: *mut Content) {
         // TODO: Async?
         let mut textures = HashMap::new();
@@ -0,0 +1,27 @@
+use engine::ecs::{EntityId, World};
+
+// `entity` started touching `other`, sent once per side of the collision
+#[derive(Clone, Debug)]
+pub struct CollisionStarted {
+    pub entity: EntityId,
+    pub other: EntityId,
+}
+
+#[derive(Clone, Debug)]
+pub struct ButtonPressed {
+    pub entity: EntityId,
+    pub name: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct RoomEntered {
+    pub x: i32,
+    pub y: i32,
+}
+
+// Called on startup and after every hot reload, so event types added by the new lib exist
+pub fn register(world: &mut World) {
+    world.add_event::<CollisionStarted>();
+    world.add_event::<ButtonPressed>();
+    world.add_event::<RoomEntered>();
+}
@@ -1,20 +1,28 @@
-use common::{Debug, Keyboard};
+use common::{Debug, MAX_GAMEPADS};
 use engine::{
-    ecs::World,
+    ecs::{Children, EntityId, Parent, Schedule, Stage, World, WorldOp},
     graphics::{
         self, batch::Batch, blend, common::RectF, material::Material, texture::TextureSampler,
     },
 };
+use serde_yml::Value;
 
 use crate::{
-    components::{button::Button, light::LightSwitch, room::Room},
+    components::{
+        self,
+        button::Button, light::LightSwitch, player::Player, position::Position, room::Room,
+    },
     content::Content,
-    scene::Scene,
+    events,
+    input::{Bindings, Input},
+    reload::StateSnapshot,
+    save_state::SaveState,
+    scene::{GameScene, Scene},
     system::{
-        animation_system::AnimationSystem, editor::Editor, light_system::LightSystem, movement_system::MovementSystem, player_system::PlayerSystem, render_system::RenderSystem, room_render_system::RoomRenderSystem, scene_system::SceneSystem
+        animation_system::AnimationSystem, editor::Editor, light_system::LightSystem, movement_system::MovementSystem, player_system::PlayerSystem, render_system::RenderSystem, room_render_system::RoomRenderSystem, scene_system::SceneSystem, transform_system::TransformSystem
     },
     target_manager::TargetManager,
-    MEMORY_PTR,
+    GAME_STATE_ROOT, MEMORY_PTR,
 };
 
 pub const ROOM_COUNT_W: usize = 4;
@@ -22,6 +30,8 @@ pub const ROOM_COUNT_H: usize = 4;
 pub const SCREEN_WIDTH: usize = GAME_PIXEL_WIDTH * 4;
 pub const SCREEN_HEIGHT: usize = GAME_PIXEL_HEIGHT * 4;
 
+pub const TICK_RATE: u32 = 60; // Simulation ticks per second
+
 pub const TILE_SIZE: usize = 8;
 pub const GAME_PIXEL_WIDTH: usize = 320;
 pub const GAME_PIXEL_HEIGHT: usize = 184;
@@ -33,28 +43,135 @@ pub const CRT_FRAGMENT_SOURCE: &str = include_str!("crt_shader.fs");
 
 #[repr(C)]
 pub struct GameState {
-    world: World,
+    pub world: World,
     batch: Batch,
     movement_system: MovementSystem,
     render_system: RenderSystem,
     player_system: PlayerSystem,
     pub scene_system: SceneSystem,
     light_system: LightSystem,
-    screen_rect: RectF,
     post_processing_material: Material,
     show_editor: bool,
     editor: Editor,
     pub target_manager: TargetManager, 
+    pub input: Input,
+    pub schedule: Schedule<GameState>,
+    // An entity copied in the inspector, as written by ComponentRegistry::entity_to_yaml
+    inspector_clipboard: Option<String>,
+}
+
+impl AsMut<World> for GameState {
+    fn as_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+}
+
+fn editor_closed(game: &GameState) -> bool {
+    !game.show_editor
 }
 
 impl GameState {
+    // Part of reload::STATE_LAYOUT, list new fields here
+    pub const LAYOUT: u64 = common::struct_layout!(
+        GameState;
+        world,
+        batch,
+        movement_system,
+        render_system,
+        player_system,
+        scene_system,
+        light_system,
+        post_processing_material,
+        show_editor,
+        editor,
+        target_manager,
+        input,
+        schedule,
+        inspector_clipboard,
+    );
+
     pub fn init_systems(&mut self) {
         self.player_system.init(&mut self.world);
         self.scene_system.scene.init(&mut self.world);
     }
 
+    // Built again after every hot reload (the systems are code), their state lives in GameState
+    pub fn build_schedule() -> Schedule<GameState> {
+        let mut schedule = Schedule::new();
+        // Make sure we are in the right screen
+        schedule
+            .add(Stage::PreUpdate, "scene", |game: &mut GameState| game.scene_system.update(&mut game.world))
+            .run_if(editor_closed);
+        // Control / update player
+        schedule
+            .add(Stage::Update, "player", |game: &mut GameState| game.player_system.update(&mut game.world))
+            .run_if(editor_closed);
+        // Actually move stuff
+        schedule
+            .add(Stage::Update, "movement", |game: &mut GameState| game.movement_system.update(&mut game.world))
+            .after("player")
+            .run_if(editor_closed);
+        schedule
+            .add(Stage::Update, "editor", |game: &mut GameState| game.editor.update())
+            .run_if(|game| game.show_editor);
+        schedule
+            .add(Stage::PostUpdate, "buttons", |game: &mut GameState| Button::update(&mut game.world))
+            .run_if(editor_closed);
+        schedule
+            .add(Stage::PostUpdate, "light_switches", |game: &mut GameState| LightSwitch::update(&game.world))
+            .after("buttons")
+            .run_if(editor_closed);
+        // Attached entities (the player's light) follow their parent
+        schedule.add(Stage::PostUpdate, "transforms", |game: &mut GameState| TransformSystem::update(&mut game.world));
+        schedule.add(Stage::PostUpdate, "animation", |game: &mut GameState| AnimationSystem::tick(&game.world));
+        schedule.add(Stage::Render, "rooms", |game: &mut GameState| {
+            RoomRenderSystem::render(&mut game.batch, &mut game.target_manager)
+        });
+        // Render into low-res target
+        schedule
+            .add(Stage::Render, "sprites", |game: &mut GameState| game.render_sprites())
+            .after("rooms");
+        schedule
+            .add(Stage::Render, "lights", |game: &mut GameState| {
+                game.light_system
+                    .render(&game.world, &mut game.batch, &mut game.target_manager.lights);
+                game.batch.clear();
+            })
+            .after("sprites");
+        schedule
+            .add(Stage::Render, "post_processing", |game: &mut GameState| game.post_process())
+            .after("lights");
+        // Finally, render low-res target onto the screen
+        schedule
+            .add(Stage::Render, "screen", |game: &mut GameState| game.render_screen())
+            .after("post_processing");
+        schedule
+    }
+
+    fn run_schedule(&mut self, stage: Stage) {
+        // Systems get the whole GameState, the schedule can't stay borrowed from it meanwhile
+        let mut schedule = std::mem::take(&mut self.schedule);
+        schedule.run(stage, self);
+        self.schedule = schedule;
+    }
+
+    fn schedule_debug(&mut self) {
+        Debug::window("Systems");
+        for (name, stage, enabled) in self.schedule.systems() {
+            let name = name.to_string();
+            Debug::checkbox(
+                &format!("{} ({:?})", name, stage),
+                enabled,
+                Box::new(move || {
+                    let schedule = &mut GameState::get().schedule;
+                    schedule.set_enabled(&name, !schedule.is_enabled(&name));
+                }),
+            );
+        }
+    }
+
     pub fn get() -> &'static mut Self {
-        unsafe { &mut *((*MEMORY_PTR).storage.as_mut_ptr() as *mut GameState) }
+        unsafe { &mut *((*MEMORY_PTR).roots[GAME_STATE_ROOT] as *mut GameState) }
     }
 
     pub fn current_room() -> &'static Room {
@@ -67,8 +184,10 @@ impl GameState {
     }
 
     pub fn new() -> Self {
-        let world = World::new();
-        let player_system = PlayerSystem;
+        let mut world = World::new();
+        events::register(&mut world);
+        world.add_resource(Bindings::load_or_default());
+        let player_system = PlayerSystem::default();
 
         let scene_system = SceneSystem::new();
 
@@ -95,16 +214,80 @@ impl GameState {
         Self {
             world,
             batch,
-            movement_system: MovementSystem,
+            movement_system: MovementSystem::default(),
             render_system,
             player_system,
             scene_system,
             light_system,
-            screen_rect: RectF::with_size(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32),
             post_processing_material,
             show_editor: false,
             editor: Editor::default(),
             target_manager,
+            input: Input::new(),
+            schedule: GameState::build_schedule(),
+            inspector_clipboard: None,
+        }
+    }
+
+    // What survives a hot reload that changes the GameState layout
+    pub fn snapshot(&self) -> StateSnapshot {
+        let player_position = self.world.first::<Player>().map(|player| {
+            let position = player.get::<Position>();
+            (position.x, position.y)
+        });
+        StateSnapshot {
+            player_position,
+            room: (self.scene_system.scene.room_x, self.scene_system.scene.room_y),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &StateSnapshot) {
+        let (room_x, room_y) = snapshot.room;
+        if (room_x, room_y) != (self.scene_system.scene.room_x, self.scene_system.scene.room_y) {
+            self.scene_system.scene.destroy(&mut self.world);
+            self.scene_system.scene = GameScene::with_room(room_x, room_y);
+            self.scene_system.scene.init(&mut self.world);
+        }
+        if let Some((x, y)) = snapshot.player_position {
+            if let Some(player) = self.world.first::<Player>() {
+                let mut position = player.get::<Position>();
+                position.x = x;
+                position.y = y;
+            }
+        }
+    }
+
+    pub fn save_state(&self, slot: u8) -> Result<(), String> {
+        let save_state = SaveState {
+            world: components::registry().save(&self.world)?,
+            scene: self.scene_system.scene.clone(),
+        };
+        save_state.write(slot)
+    }
+
+    pub fn load_state(&mut self, slot: u8) -> Result<(), String> {
+        let save_state = SaveState::read(slot)?;
+        components::registry().restore(&mut self.world, save_state.world)?;
+        // The room entities came back with the world, the scene only needs to know their ids
+        self.scene_system.scene = save_state.scene;
+        self.scene_system.initialised = true;
+        Ok(())
+    }
+
+    // Save state hotkeys, handled here and not in update so they also work while paused
+    fn handle_save_slots(&mut self) {
+        let save_slots = crate::save_slots();
+        if let Some(slot) = save_slots.save.take() {
+            match self.save_state(slot) {
+                Ok(()) => println!("Saved state to {:?}", SaveState::path(slot)),
+                Err(error) => println!("Failed to save state to slot {}: {}", slot, error),
+            }
+        }
+        if let Some(slot) = save_slots.load.take() {
+            match self.load_state(slot) {
+                Ok(()) => println!("Loaded state from {:?}", SaveState::path(slot)),
+                Err(error) => println!("Failed to load state from slot {}: {}", slot, error),
+            }
         }
     }
 
@@ -139,85 +322,200 @@ impl GameState {
         game_state.batch.clear();
     }
 
+    /**
+     * The player and what is attached to it, bool fields can be toggled.
+     * Entities can be copied and pasted as new ones, a pasted child is attached to the same parent.
+     */
+    fn inspector_debug(&self) {
+        Debug::window("Inspector");
+        if let Some(yaml) = &self.inspector_clipboard {
+            Debug::display(&format!("Clipboard:\n{}", yaml.trim_end()));
+            Debug::labeled_button(
+                "Paste as new entity",
+                Box::new(|| {
+                    if let Err(error) = GameState::get().paste_entity() {
+                        println!("Failed to paste entity: {}", error);
+                    }
+                }),
+            );
+        }
+        let Some(player) = self.world.first::<Player>().map(|player| player.id) else {
+            return;
+        };
+        let registry = components::registry();
+        let mut entities = vec![player];
+        entities.extend(self.world.children(player));
+        for entity in entities {
+            Debug::separator();
+            Debug::display(&format!("Entity {}:{}", entity.index, entity.generation));
+            Debug::labeled_button(
+                &format!("Copy##{:?}", entity),
+                Box::new(move || {
+                    let game_state = GameState::get();
+                    match registry.entity_to_yaml(&game_state.world, entity) {
+                        Ok(yaml) => game_state.inspector_clipboard = Some(yaml),
+                        Err(error) => println!("Failed to copy entity {:?}: {}", entity, error),
+                    }
+                }),
+            );
+            for name in registry.component_names(&self.world, entity) {
+                Debug::display(name);
+                let result = registry.visit_fields(&self.world, entity, name, &mut |path, value| match value {
+                    Value::Bool(value) => {
+                        let (path, value) = (path.to_string(), *value);
+                        Debug::checkbox(
+                            &format!("  {}##{:?}{}", path, entity, name),
+                            value,
+                            Box::new(move || {
+                                let world = &mut GameState::get().world;
+                                let result = registry.set_field(world, entity, name, &path, Value::Bool(!value));
+                                if let Err(error) = result {
+                                    println!("Failed to set {}.{}: {}", name, path, error);
+                                }
+                            }),
+                        );
+                    }
+                    value => {
+                        let value = serde_yml::to_string(value).unwrap_or_default();
+                        Debug::display(&format!("  {}: {}", path, value.trim()));
+                    }
+                });
+                if let Err(error) = result {
+                    Debug::display(&format!("  {}", error));
+                }
+            }
+        }
+    }
+
+    // Spawns a copy of the entity in the inspector clipboard
+    fn paste_entity(&mut self) -> Result<EntityId, String> {
+        let yaml = self.inspector_clipboard.as_ref().ok_or("Nothing was copied")?;
+        let entity = self.world.add_entity().id;
+        if let Err(error) = components::registry().entity_from_yaml(&mut self.world, entity, yaml) {
+            self.world.remove_entity(entity);
+            return Err(error);
+        }
+        // The copy doesn't own the original's children, and has to be listed in its parent's Children
+        self.world.remove_component::<Children>(entity);
+        if let Some(Parent(parent)) = self.world.extract_component::<Parent>(entity) {
+            self.world.set_parent(entity, parent);
+        }
+        Ok(entity)
+    }
+
+    fn memory_debug() {
+        let memory = unsafe { &*MEMORY_PTR };
+        Debug::window("Memory");
+        for (name, arena) in [("Persistent", &memory.persistent), ("Scratch", &memory.scratch)] {
+            Debug::display(&format!(
+                "{}: {} / {} bytes (max {})",
+                name,
+                arena.used(),
+                arena.capacity(),
+                arena.max_size()
+            ));
+            Debug::display(&format!(
+                "  {} allocations, {} chunks, peak {} bytes",
+                arena.allocations(),
+                arena.chunk_count(),
+                arena.peak()
+            ));
+        }
+    }
+
     pub fn update(&mut self) -> bool {
-        if Keyboard::pressed(engine::Keycode::Tab) {
+        Input::update();
+        if Input::pressed("toggle_editor") {
             dbg!("show editor pressed");
             self.show_editor = !self.show_editor;
         }
+        // Debug windows are built per tick, the runtime keeps showing the last ones on frames without ticks
         Debug::window("Game");
         Debug::display(&"Press tab to toggle editor");
         Debug::separator();
         Debug::display(&format!("Showing editor: {} ", self.show_editor));
+        Debug::playback();
+        Debug::display(&format!("Ticks this frame: {}", crate::time().ticks_this_frame));
+        Debug::display(
+            format!("Current room camera ortho {}", &GameState::current_room().camera_ortho).as_str(),
+        );
+        let display = crate::display();
+        Debug::display(&format!(
+            "Window {}x{}, drawable {}x{}{}",
+            display.window_size.0,
+            display.window_size.1,
+            display.drawable_size.0,
+            display.drawable_size.1,
+            if display.fullscreen { " (fullscreen)" } else { "" }
+        ));
+        Debug::display(&format!(
+            "Mouse (game pixels): {:?}",
+            TargetManager::screen_to_game(display, crate::mouse().position())
+        ));
+        let gamepads: Vec<usize> = (0..MAX_GAMEPADS).filter(|slot| crate::gamepad().connected(*slot)).collect();
+        Debug::display(&format!("Gamepads connected: {:?}", gamepads));
+        RoomRenderSystem::debug(&self.target_manager);
+        GameState::memory_debug();
+        Input::debug();
+        self.schedule_debug();
+        self.inspector_debug();
 
-        if !self.show_editor {
-            // Make sure we are in the right screen
-            self.scene_system.update(&mut self.world);
-            // Control / update player
-            self.player_system.update(&mut self.world);
-            // Actually move stuff
-            self.movement_system.update(&mut self.world);
-            Button::update(&mut self.world);
-            LightSwitch::update(&mut self.world);
-        } else {
-            self.editor.update();
-        }
+        // Events sent two ticks ago are dropped here
+        self.world.update_events();
+        // Structural changes queued by the systems are applied at the end of each stage
+        self.run_schedule(Stage::PreUpdate);
+        self.run_schedule(Stage::Update);
+        self.run_schedule(Stage::PostUpdate);
         true
     }
 
     pub fn render(&mut self) {
+        self.handle_save_slots();
         engine::update();
-        RoomRenderSystem::render(&mut self.batch, &mut self.target_manager);
+        self.target_manager.fit_screen(crate::display());
+        self.run_schedule(Stage::Render);
+    }
 
-        // Render into low-res target
-        {
-            self.target_manager
-                .game
-                .clear((0.1f32, 0.1f32, 0.24f32, 1.0f32));
-            self.batch.set_sampler(&TextureSampler::nearest());
-            AnimationSystem::tick(&self.world); // ??
-
-            self.batch.set_blend(blend::NORMAL);
-            self.render_system
-                .render(&self.world, &mut self.batch, &self.target_manager.color);
-            self.batch.clear();
-
-            self.light_system.render(
-                &self.world,
-                &mut self.batch,
-                &mut self.target_manager.lights,
-            );
+    fn render_sprites(&mut self) {
+        self.target_manager
+            .game
+            .clear((0.1f32, 0.1f32, 0.24f32, 1.0f32));
+        self.batch.set_sampler(&TextureSampler::nearest());
+
+        self.batch.set_blend(blend::NORMAL);
+        self.render_system
+            .render(&self.world, &mut self.batch, &self.target_manager.color);
+        self.batch.clear();
+    }
 
-            self.batch.clear();
+    // Render the 'color' + 'lighting' into the final 'game' frame target
+    fn post_process(&mut self) {
+        self.batch.push_material(&self.post_processing_material);
+        self.batch.tex(
+            &RectF::with_size(GAME_PIXEL_WIDTH as f32, GAME_PIXEL_HEIGHT as f32),
+            self.target_manager.color.color(),
+            (1.0f32, 1.0f32, 1.0f32, 1f32),
+        );
+        self.batch.render(&self.target_manager.game);
+        self.batch.pop_material();
 
-            // Render the 'color' + 'lighting' into the final 'game' frame target
-            self.batch.push_material(&self.post_processing_material);
+        self.batch.clear();
+    }
+
+    fn render_screen(&mut self) {
+        self.batch.set_sampler(&TextureSampler::nearest());
+        if self.show_editor {
+            self.editor.render(&mut self.batch, &self.target_manager);
+        } else {
             self.batch.tex(
-                &RectF::with_size(GAME_PIXEL_WIDTH as f32, GAME_PIXEL_HEIGHT as f32),
-                self.target_manager.color.color(),
+                &TargetManager::letterbox(crate::display()),
+                self.target_manager.game.color(),
                 (1.0f32, 1.0f32, 1.0f32, 1f32),
             );
-            self.batch.render(&self.target_manager.game);
-            self.batch.pop_material();
-
-            self.batch.clear();
         }
 
-        // Finally, render low-res target onto the screen
-        {
-            self.batch.set_sampler(&TextureSampler::nearest());
-            if self.show_editor {
-                self.editor.render(&mut self.batch, &self.target_manager);
-            } else {
-                self.batch.tex(
-                    &self.screen_rect,
-                    self.target_manager.game.color(),
-                    (1.0f32, 1.0f32, 1.0f32, 1f32),
-                );
-            }
-
-            self.target_manager.screen.clear((0f32, 0f32, 0f32, 1f32));
-            self.batch.render(&self.target_manager.screen);
-            self.batch.clear();
-        }
+        self.target_manager.screen.clear((0f32, 0f32, 0f32, 1f32));
+        self.batch.render(&self.target_manager.screen);
+        self.batch.clear();
     }
 }
@@ -0,0 +1,332 @@
+use std::{cell::Ref, collections::BTreeMap, fmt, fs};
+
+use common::{Debug, GamepadAxis, GamepadButton, Scancode};
+use serde::{Deserialize, Serialize};
+
+use crate::{gamepad, game_state::GameState, keyboard, mouse, text_input};
+
+const BINDINGS_PATH: &str = "config/bindings.yml";
+// How far a stick has to be pushed for an axis binding to count as held
+const AXIS_HELD_THRESHOLD: f32 = 0.5;
+
+// Gamepad buttons a rebind listens to
+const REBIND_BUTTONS: [GamepadButton; 15] = [
+    GamepadButton::A,
+    GamepadButton::B,
+    GamepadButton::X,
+    GamepadButton::Y,
+    GamepadButton::Back,
+    GamepadButton::Guide,
+    GamepadButton::Start,
+    GamepadButton::LeftStick,
+    GamepadButton::RightStick,
+    GamepadButton::LeftShoulder,
+    GamepadButton::RightShoulder,
+    GamepadButton::DPadUp,
+    GamepadButton::DPadDown,
+    GamepadButton::DPadLeft,
+    GamepadButton::DPadRight,
+];
+
+/**
+ * A key, mouse button or gamepad input an action can be bound to.
+ * Written to the bindings file as "key:Up" (scancode names), "mouse:left", "pad:a" or "pad:-leftx" (stick pushed left)
+ */
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Source {
+    Key(Scancode),
+    MouseLeft,
+    MouseRight,
+    Button(GamepadButton),
+    Axis(GamepadAxis, bool), // true for the positive direction
+}
+
+impl Source {
+    // 0..1, only gamepad axes give values in between
+    fn value(&self) -> f32 {
+        match self {
+            // Keys typed into a text field don't drive gameplay
+            Source::Key(_) if text_input().is_active() => 0.0,
+            Source::Key(key) => keyboard().held(*key) as u8 as f32,
+            Source::MouseLeft => mouse().left_held() as u8 as f32,
+            Source::MouseRight => mouse().right_held() as u8 as f32,
+            Source::Button(button) => gamepad().any_held(*button) as u8 as f32,
+            Source::Axis(axis, positive) => {
+                let value = gamepad().any_axis(*axis);
+                if *positive { value.max(0.0) } else { (-value).max(0.0) }
+            }
+        }
+    }
+
+    fn held(&self) -> bool {
+        match self {
+            Source::Axis(..) => self.value() >= AXIS_HELD_THRESHOLD,
+            _ => self.value() > 0.0,
+        }
+    }
+
+    // Axes don't have a press edge, bind them to actions that are read with held / value / axis
+    fn pressed(&self) -> bool {
+        match self {
+            Source::Key(_) if text_input().is_active() => false,
+            Source::Key(key) => keyboard().pressed(*key),
+            Source::MouseLeft => mouse().left_pressed(),
+            Source::MouseRight => mouse().right_pressed(),
+            Source::Button(button) => gamepad().any_pressed(*button),
+            Source::Axis(..) => false,
+        }
+    }
+
+    // Whatever went down this tick, used to rebind actions
+    fn first_pressed() -> Option<Source> {
+        if let Some(key) = keyboard().pressed_keys().next() {
+            return Some(Source::Key(key));
+        }
+        if mouse().left_pressed() {
+            return Some(Source::MouseLeft);
+        }
+        if mouse().right_pressed() {
+            return Some(Source::MouseRight);
+        }
+        REBIND_BUTTONS
+            .iter()
+            .find(|button| gamepad().any_pressed(**button))
+            .map(|button| Source::Button(*button))
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Key(key) => write!(f, "key:{}", key.name()),
+            Source::MouseLeft => write!(f, "mouse:left"),
+            Source::MouseRight => write!(f, "mouse:right"),
+            Source::Button(button) => write!(f, "pad:{}", button.string()),
+            Source::Axis(axis, positive) => {
+                write!(f, "pad:{}{}", if *positive { "+" } else { "-" }, axis.string())
+            }
+        }
+    }
+}
+
+impl From<Source> for String {
+    fn from(source: Source) -> Self {
+        source.to_string()
+    }
+}
+
+impl TryFrom<String> for Source {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("Invalid input binding '{}'", value);
+        let (device, name) = value.split_once(':').ok_or_else(invalid)?;
+        match (device, name) {
+            ("key", name) => Scancode::from_name(name).map(Source::Key).ok_or_else(invalid),
+            ("mouse", "left") => Ok(Source::MouseLeft),
+            ("mouse", "right") => Ok(Source::MouseRight),
+            ("pad", name) if name.starts_with('+') || name.starts_with('-') => {
+                GamepadAxis::from_string(&name[1..])
+                    .map(|axis| Source::Axis(axis, name.starts_with('+')))
+                    .ok_or_else(invalid)
+            }
+            ("pad", name) => GamepadButton::from_string(name)
+                .map(Source::Button)
+                .ok_or_else(invalid),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/**
+ * What lives in the bindings file.
+ * Actions map a name to the inputs that trigger it, axes combine two actions into a -1..1 value.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Bindings {
+    pub actions: BTreeMap<String, Vec<Source>>,
+    pub axes: BTreeMap<String, (String, String)>, // (negative action, positive action)
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let actions = [
+            ("jump", vec![Source::Key(Scancode::Up), Source::Button(GamepadButton::A)]),
+            (
+                "left",
+                vec![
+                    Source::Key(Scancode::Left),
+                    Source::Button(GamepadButton::DPadLeft),
+                    Source::Axis(GamepadAxis::LeftX, false),
+                ],
+            ),
+            (
+                "right",
+                vec![
+                    Source::Key(Scancode::Right),
+                    Source::Button(GamepadButton::DPadRight),
+                    Source::Axis(GamepadAxis::LeftX, true),
+                ],
+            ),
+            ("attack", vec![Source::Key(Scancode::Space), Source::Button(GamepadButton::X)]),
+            ("toggle_editor", vec![Source::Key(Scancode::Tab), Source::Button(GamepadButton::Back)]),
+            ("editor_command", vec![Source::Key(Scancode::Return)]),
+            ("editor_debug_textures", vec![Source::Key(Scancode::Space)]),
+            ("editor_save", vec![Source::Key(Scancode::S)]),
+            ("editor_paint", vec![Source::MouseLeft]),
+            ("editor_pan", vec![Source::MouseRight]),
+        ];
+        Bindings {
+            actions: actions
+                .into_iter()
+                .map(|(name, sources)| (name.to_string(), sources))
+                .collect(),
+            axes: BTreeMap::from([("walk".to_string(), ("left".to_string(), "right".to_string()))]),
+        }
+    }
+}
+
+impl Bindings {
+    pub fn load() -> Result<Bindings, String> {
+        let data = fs::read_to_string(BINDINGS_PATH).map_err(|e| e.to_string())?;
+        let mut bindings: Bindings = serde_yml::from_str(&data).map_err(|e| e.to_string())?;
+        // Actions added to the game after the file was written keep their default bindings
+        let defaults = Bindings::default();
+        for (name, sources) in defaults.actions {
+            bindings.actions.entry(name).or_insert(sources);
+        }
+        for (name, actions) in defaults.axes {
+            bindings.axes.entry(name).or_insert(actions);
+        }
+        Ok(bindings)
+    }
+
+    // The bindings file, or the defaults if it can't be read
+    pub fn load_or_default() -> Bindings {
+        Bindings::load().unwrap_or_else(|error| {
+            println!("Using default input bindings ({}): {}", BINDINGS_PATH, error);
+            Bindings::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let data = serde_yml::to_string(self).map_err(|e| e.to_string())?;
+        fs::create_dir_all("config/").map_err(|e| e.to_string())?;
+        fs::write(BINDINGS_PATH, data).map_err(|e| e.to_string())
+    }
+}
+
+/**
+ * Named actions on top of Keyboard / Mouse / Gamepad, e.g. Input::pressed("jump").
+ * The Bindings are a World resource. Querying an action that isn't bound is a bug and panics.
+ */
+pub struct Input {
+    rebinding: Option<String>, // Action waiting for the next key / button press
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Input { rebinding: None }
+    }
+
+    fn get() -> &'static mut Input {
+        &mut GameState::get().input
+    }
+
+    fn bindings() -> Ref<'static, Bindings> {
+        GameState::get().world.resource::<Bindings>()
+    }
+
+    fn sources(action: &str) -> Ref<'static, [Source]> {
+        Ref::map(Self::bindings(), |bindings| {
+            bindings
+                .actions
+                .get(action)
+                .unwrap_or_else(|| panic!("Unknown input action '{}'", action))
+                .as_slice()
+        })
+    }
+
+    // The action went down this tick (one time event)
+    pub fn pressed(action: &str) -> bool {
+        Self::sources(action).iter().any(|source| source.pressed())
+    }
+
+    pub fn held(action: &str) -> bool {
+        Self::sources(action).iter().any(|source| source.held())
+    }
+
+    // 0..1, strongest of the bound inputs (keys and buttons are either 0 or 1)
+    pub fn value(action: &str) -> f32 {
+        Self::sources(action)
+            .iter()
+            .map(|source| source.value())
+            .fold(0.0, f32::max)
+    }
+
+    // -1..1, positive action minus negative action
+    pub fn axis(axis: &str) -> f32 {
+        let (negative, positive) = Self::bindings()
+            .axes
+            .get(axis)
+            .cloned()
+            .unwrap_or_else(|| panic!("Unknown input axis '{}'", axis));
+        (Self::value(&positive) - Self::value(&negative)).clamp(-1.0, 1.0)
+    }
+
+    // Replaces every binding of `action`
+    pub fn bind(action: &str, sources: Vec<Source>) {
+        GameState::get()
+            .world
+            .resource_mut::<Bindings>()
+            .actions
+            .insert(action.to_string(), sources);
+    }
+
+    // The next key / mouse / gamepad button pressed becomes the only binding of `action`
+    pub fn rebind(action: &str) {
+        Self::get().rebinding = Some(action.to_string());
+    }
+
+    // Call once per tick, before anything reads actions
+    pub fn update() {
+        let input = Self::get();
+        let Some(action) = input.rebinding.clone() else {
+            return;
+        };
+        if let Some(source) = Source::first_pressed() {
+            println!("Bound {} to {}", action, source);
+            Self::bind(&action, vec![source]);
+            input.rebinding = None;
+        }
+    }
+
+    pub fn debug() {
+        let input = Self::get();
+        let bindings = Self::bindings();
+        Debug::window("Input");
+        for (action, sources) in bindings.actions.iter() {
+            let sources: Vec<String> = sources.iter().map(|source| source.to_string()).collect();
+            Debug::display(&format!("{}: {}", action, sources.join(", ")));
+            let name = action.clone();
+            let listening = input.rebinding.as_ref() == Some(action);
+            Debug::checkbox(
+                &format!("Rebind {}", action),
+                listening,
+                Box::new(move || Input::rebind(&name)),
+            );
+        }
+        Debug::separator();
+        for (axis, (negative, positive)) in bindings.axes.iter() {
+            Debug::display(&format!("{}: {} / {} = {:.2}", axis, negative, positive, Input::axis(axis)));
+        }
+        Debug::labeled_button(
+            &format!("Save to {}", BINDINGS_PATH),
+            Box::new(|| match Input::bindings().save() {
+                Ok(()) => println!("Saved input bindings to {}", BINDINGS_PATH),
+                Err(error) => println!("Failed to save input bindings: {}", error),
+            }),
+        );
+    }
+}
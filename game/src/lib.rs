@@ -1,104 +1,269 @@
 #![allow(warnings)]
 mod components;
 mod content;
+mod events;
 mod game_state;
+mod input;
 mod scene;
 mod system;
 mod target_manager;
+mod text_field;
 mod map;
+mod reload;
+mod save_state;
 
 extern crate engine;
 extern crate nalgebra_glm as glm;
 use content::Content;
-use game_state::{GameState, SCREEN_HEIGHT, SCREEN_WIDTH};
-use scene::GameScene;
-use sdl2::{
-    libc::{kevent, PF_KEY},
-    AudioSubsystem, VideoSubsystem,
+use reload::{hand_over_lib_code, StateSnapshot, STATE_LAYOUT};
+use game_state::{
+    GameState, GAME_PIXEL_HEIGHT, GAME_PIXEL_WIDTH, ROOM_COUNT_H, ROOM_COUNT_W, SCREEN_HEIGHT,
+    SCREEN_WIDTH, TICK_RATE,
 };
+use scene::GameScene;
+use sdl2::{AudioSubsystem, VideoSubsystem};
 
-use common::{Debug, GameConfig, GameMemory, Keyboard, Mouse};
+use common::{
+    Debug, Display, GameApi, GameConfig, GameMemory, Gamepad, Keyboard, Mouse, SaveSlots, TextInput, Time,
+    GAME_API_VERSION, GAME_MEMORY_LAYOUT, GAME_MEMORY_ROOTS,
+};
 use components::{position::Position, room::Room};
-use std::{env, mem::size_of};
+use std::{
+    backtrace::Backtrace,
+    env,
+    panic::{self, AssertUnwindSafe},
+};
 
 // Pointer to the game memory (allocated in the main process — not in the dll)
 // "static" is scoped to this dll instance. when hot-reloading a new dll this must be re-set
 pub static mut MEMORY_PTR: *mut GameMemory = std::ptr::null_mut();
 
+// Slots in GameMemory.roots
+pub const GAME_STATE_ROOT: usize = 0;
+pub const CONTENT_ROOT: usize = 1;
+
+/**
+ * Panics can't unwind into the runtime (unwinding out of an extern "C" fn aborts the whole process).
+ * Every exported function runs inside guard, the panic hook reports what happened in GameMemory.fault
+ */
+fn guard<R>(f: impl FnOnce() -> R) -> Option<R> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => Some(result),
+        Err(payload) => {
+            unsafe {
+                if !MEMORY_PTR.is_null() && !(*MEMORY_PTR).fault.is_set() {
+                    // Panicked before the hook was installed
+                    let message = payload
+                        .downcast_ref::<&str>()
+                        .map(|message| message.to_string())
+                        .or_else(|| payload.downcast_ref::<String>().cloned())
+                        .unwrap_or("Unknown panic".to_string());
+                    (*MEMORY_PTR).fault.set(&message);
+                }
+            }
+            None
+        }
+    }
+}
+
+// The hook lives in this lib's copy of std, it goes away with the lib
+fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        let report = format!("{}\n\n{}", info, Backtrace::force_capture());
+        eprintln!("{}", report);
+        unsafe {
+            if !MEMORY_PTR.is_null() {
+                (*MEMORY_PTR).fault.set(&report);
+            }
+        }
+    }));
+}
+
+// The only symbol the runtime looks up, everything else is reached through this table
 #[no_mangle]
+pub extern "C" fn game_api() -> GameApi {
+    GameApi {
+        version: GAME_API_VERSION,
+        memory_layout: GAME_MEMORY_LAYOUT,
+        get_config,
+        init,
+        update: update_game,
+        render: render_game,
+        clear_game,
+        de_init,
+    }
+}
+
 pub extern "C" fn init(
     video_subsystem: &VideoSubsystem,
     audio_subsystem: &AudioSubsystem,
     game_memory_ptr: *mut GameMemory,
 ) {
     unsafe {
-        env::set_var("RUST_BACKTRACE", "1");
         MEMORY_PTR = game_memory_ptr; // get a pointer to the game memory
-        Keyboard::init(&mut (*MEMORY_PTR).keyboard);
-        Mouse::init(&mut (*MEMORY_PTR).mouse);
+    }
+    install_panic_hook();
+    guard(|| unsafe {
+        env::set_var("RUST_BACKTRACE", "1");
         Debug::init(&mut (*MEMORY_PTR).debug);
         engine::init(&video_subsystem, &audio_subsystem);
 
+        // Written by the previous lib (if any) right before it got unloaded
+        let snapshot = StateSnapshot::load(&(*MEMORY_PTR).reload_snapshot);
+        (*MEMORY_PTR).reload_snapshot.clear();
+
         if (*MEMORY_PTR).initialized {
-            GameState::refresh();
-            return;
+            if (*MEMORY_PTR).state_layout == STATE_LAYOUT {
+                hand_over_lib_code(Some(GameState::get()));
+                GameState::refresh();
+                return;
+            }
+            hand_over_lib_code(None);
+            // The state was written by a lib with different types, the code that could drop it
+            // is gone with that lib. Leak whatever it owns and start from scratch.
+            println!(
+                "GameState layout changed ({:#018x} -> {:#018x}), rebuilding game state",
+                (*MEMORY_PTR).state_layout,
+                STATE_LAYOUT
+            );
+            (*MEMORY_PTR).initialized = false;
+            (*MEMORY_PTR).persistent.reset();
         }
 
-        let game_size = size_of::<GameState>(); // ~1232 bytes
-        let available_memory = (*MEMORY_PTR).storage.len();
-        assert!(
-            game_size <= available_memory,
-            "Game is too large for game_memory storage. Game size: {}, available mem: {}",
-            game_size,
-            available_memory
-        );
-
-        // storage is initialized with GameState and Content [ [GameState] [Content] ]
-        let storage_ptr = (*MEMORY_PTR).storage.as_mut_ptr() as *mut GameState;
-        let content_ptr = storage_ptr.add(size_of::<GameState>()) as *mut Content;
-
+        // Content goes first, GameState::new() already reads from it
+        let persistent = &mut (*MEMORY_PTR).persistent;
+        let content_ptr = persistent
+            .alloc_uninit::<Content>()
+            .expect("Content does not fit in persistent game memory");
+        (*MEMORY_PTR).roots[CONTENT_ROOT] = content_ptr as *mut u8;
         Content::load(content_ptr);
-        storage_ptr.write(GameState::new()); // Directly write Game into storage
+
+        let game_ptr = (*MEMORY_PTR)
+            .persistent
+            .alloc(GameState::new())
+            .expect("GameState does not fit in persistent game memory");
+        (*MEMORY_PTR).roots[GAME_STATE_ROOT] = game_ptr as *mut u8;
 
         (*MEMORY_PTR).initialized = true;
+        (*MEMORY_PTR).state_layout = STATE_LAYOUT;
         GameState::get().init_systems();
-    }
+        if let Some(snapshot) = snapshot {
+            println!("Restoring {:?}", snapshot);
+            GameState::get().restore(&snapshot);
+        } else if let Some(room) = (*MEMORY_PTR).start_room {
+            let (x, y) = room;
+            if (0..ROOM_COUNT_W as i32).contains(&x) && (0..ROOM_COUNT_H as i32).contains(&y) {
+                println!("Starting in room {:?}", room);
+                GameState::get().restore(&StateSnapshot {
+                    player_position: None,
+                    room,
+                });
+            } else {
+                println!("Room {:?} is outside the {}x{} map", room, ROOM_COUNT_W, ROOM_COUNT_H);
+            }
+        }
+    });
 }
 
-#[no_mangle]
+// Only returns constants, nothing to guard
 pub extern "C" fn get_config() -> GameConfig {
     GameConfig {
         window_width: SCREEN_WIDTH as u32,
         window_height: SCREEN_HEIGHT as u32,
+        game_width: GAME_PIXEL_WIDTH as u32,
+        game_height: GAME_PIXEL_HEIGHT as u32,
+        tick_rate: TICK_RATE,
     }
 }
 
-// TODO: pass a pointer to the keyboard instead — make it globally accessible throught the game
-// instead of passing it around to every single function
-#[no_mangle]
+// Timing data written by the runtime (tick count, interpolation alpha)
+pub fn time() -> &'static Time {
+    unsafe { &(*MEMORY_PTR).time }
+}
+
+// Window / drawable size written by the runtime, follows resizes and fullscreen
+pub fn display() -> &'static Display {
+    unsafe { &(*MEMORY_PTR).display }
+}
+
+// Input devices live in GameMemory and are read through MEMORY_PTR, which every lib sets in init
+// before anything else runs. Nothing keeps pointers into a previous lib or a previous GameMemory.
+pub fn keyboard() -> &'static Keyboard {
+    unsafe { &(*MEMORY_PTR).keyboard }
+}
+
+pub fn mouse() -> &'static Mouse {
+    unsafe { &(*MEMORY_PTR).mouse }
+}
+
+// Mutable so the game can request rumble
+pub fn gamepad() -> &'static mut Gamepad {
+    unsafe { &mut (*MEMORY_PTR).gamepad }
+}
+
+// Mutable so the game can start / stop text input
+pub fn text_input() -> &'static mut TextInput {
+    unsafe { &mut (*MEMORY_PTR).text_input }
+}
+
+/**
+ * Copies `values` into the scratch arena, which the runtime resets at the start of every frame.
+ * For per-frame data only: the slice must not be kept past the frame it was allocated in.
+ */
+pub fn scratch_slice<T: Copy>(values: impl ExactSizeIterator<Item = T>) -> &'static mut [T] {
+    unsafe {
+        let slice = (*MEMORY_PTR)
+            .scratch
+            .alloc_iter(values)
+            .expect("Per-frame data does not fit in scratch game memory");
+        &mut *slice
+    }
+}
+
+// Mutable so the game can clear the requests it handled
+pub fn save_slots() -> &'static mut SaveSlots {
+    unsafe { &mut (*MEMORY_PTR).save_slots }
+}
+
+// Called once per simulation tick, possibly several times (or not at all) per frame
 pub extern "C" fn update_game() {
-    let game =  GameState::get();
-    game.update();
-    game.render();
+    guard(|| GameState::get().update());
+}
+
+// Called once per frame, after all the ticks for that frame ran
+pub extern "C" fn render_game() {
+    guard(|| GameState::get().render());
 }
 
-#[no_mangle]
 pub extern "C" fn de_init() {
     // Called when the game lib is about to be dropped or reloaded
+    guard(|| unsafe {
+        // Hand the layout independent parts of the state to the next lib, in case it can't reuse storage
+        if !MEMORY_PTR.is_null() && (*MEMORY_PTR).initialized {
+            GameState::get()
+                .snapshot()
+                .save(&mut (*MEMORY_PTR).reload_snapshot);
+        }
+    });
+    guard(|| {
+        // This does not delete the game memory — it only clears things in the game library itself
+        // Mainly the static audio lib
+        engine::deinit();
+    });
     unsafe {
         // This is a bit pointless sience the lib is getting destroyed but let's do it anyways
         MEMORY_PTR = std::ptr::null_mut();
     }
-    // This does not delete the game memory — it only clears things in the game library itself
-    // Mainly the static audio lib
-    engine::deinit()
 }
 
-#[no_mangle]
 pub extern "C" fn clear_game(game_memory: &mut GameMemory) {
-    let game_ptr: *mut GameState = game_memory.storage.as_mut_ptr() as *mut GameState;
-    unsafe {
-        std::ptr::drop_in_place(game_ptr); // Drop Game manually
+    let game_ptr = game_memory.roots[GAME_STATE_ROOT] as *mut GameState;
+    if game_memory.initialized {
+        guard(|| unsafe {
+            std::ptr::drop_in_place(game_ptr); // Drop Game manually
+        });
     }
+    game_memory.roots = [std::ptr::null_mut(); GAME_MEMORY_ROOTS];
+    game_memory.persistent.reset();
     game_memory.initialized = false;
 }
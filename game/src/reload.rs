@@ -0,0 +1,74 @@
+use common::{layout_hash, Debug, ReloadSnapshot};
+use engine::ecs::World;
+use serde::{Deserialize, Serialize};
+use std::mem::size_of;
+
+use crate::{
+    components, content::Content, events, game_state::GameState, system::editor::Editor,
+    system::scene_system::SceneSystem, target_manager::TargetManager,
+};
+
+// Bump when a field is retyped in a way the layouts don't show (same size, alignment and offset)
+const STATE_VERSION: usize = 4;
+
+// Fingerprint of everything this lib keeps in the persistent arena (GameState and Content, reached
+// through GameMemory.roots): field names and offsets of the structs listed below, sizes of the rest.
+// A hot reloaded lib with a different fingerprint can't reuse the arena contents as-is.
+pub const STATE_LAYOUT: u64 = layout_hash(&[
+    STATE_VERSION,
+    GameState::LAYOUT as usize,
+    Content::LAYOUT as usize,
+    SceneSystem::LAYOUT as usize,
+    Editor::LAYOUT as usize,
+    TargetManager::LAYOUT as usize,
+    size_of::<World>(),
+]);
+
+/**
+ * Code (trait objects, closures, fn pointers) kept in memory that outlives the lib points into the lib
+ * that stored it, which is unloaded on a reload. Running or dropping it would crash, so the new lib
+ * hands every one of them over before anything else runs:
+ * - Debug windows (checkbox closures, buttons): leaked, the next frame builds them again
+ * - Schedule (systems, run conditions): rebuilt with this lib's systems, keeping which ones are enabled. The old one is leaked
+ * - World (component storages, resources, event queues, queued commands): detached, then every component / event type
+ *   attaches this lib's code. Resources attach on their first access
+ * `game_state` is None when the state is rebuilt from scratch (layout changed), its code is leaked with the arena.
+ * Anything new in GameState that keeps code around has to be handed over here too.
+ */
+pub fn hand_over_lib_code(game_state: Option<&mut GameState>) {
+    Debug::forget();
+    let Some(game_state) = game_state else {
+        return;
+    };
+    game_state.world.detach_lib_code();
+    events::register(&mut game_state.world);
+    components::registry().attach(&game_state.world);
+
+    let mut schedule = GameState::build_schedule();
+    schedule.copy_enabled(&game_state.schedule);
+    std::mem::forget(std::mem::replace(&mut game_state.schedule, schedule));
+}
+
+/**
+ * The parts of the game state that survive a reload that changes the state layout.
+ * Written by the lib being unloaded, read by the next one.
+ */
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct StateSnapshot {
+    pub player_position: Option<(i32, i32)>,
+    pub room: (i32, i32),
+}
+
+impl StateSnapshot {
+    pub fn save(&self, snapshot: &mut ReloadSnapshot) {
+        let data = serde_yml::to_string(self).unwrap();
+        if !snapshot.write(data.as_bytes()) {
+            println!("State snapshot is too large ({} bytes), dropping it", data.len());
+        }
+    }
+
+    pub fn load(snapshot: &ReloadSnapshot) -> Option<StateSnapshot> {
+        let data = std::str::from_utf8(snapshot.read()?).ok()?;
+        serde_yml::from_str(data).ok()
+    }
+}
@@ -0,0 +1,34 @@
+use engine::ecs::WorldSnapshot;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::scene::GameScene;
+
+const SAVE_DIR: &str = "saves";
+
+/**
+ * Everything needed to put the game back where it was: the whole World and the room being played.
+ * Unlike StateSnapshot (hot reloads) this is written to disk, one file per slot.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct SaveState {
+    pub world: WorldSnapshot,
+    pub scene: GameScene,
+}
+
+impl SaveState {
+    pub fn path(slot: u8) -> PathBuf {
+        PathBuf::from(SAVE_DIR).join(format!("slot{}.yml", slot))
+    }
+
+    pub fn write(&self, slot: u8) -> Result<(), String> {
+        let data = serde_yml::to_string(self).map_err(|error| error.to_string())?;
+        std::fs::create_dir_all(SAVE_DIR).map_err(|error| error.to_string())?;
+        std::fs::write(Self::path(slot), data).map_err(|error| error.to_string())
+    }
+
+    pub fn read(slot: u8) -> Result<SaveState, String> {
+        let data = std::fs::read_to_string(Self::path(slot)).map_err(|error| error.to_string())?;
+        serde_yml::from_str(&data).map_err(|error| error.to_string())
+    }
+}
@@ -1,4 +1,8 @@
-use engine::{ecs::WorldOp, graphics::common::RectF};
+use engine::{
+    ecs::{EntityId, WorldOp},
+    graphics::common::RectF,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     components::{
@@ -24,10 +28,11 @@ pub trait Scene {
     fn destroy(&mut self, _world: &mut impl WorldOp) {}
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GameScene {
     pub room_x: i32,
     pub room_y: i32,
-    entities: Vec<u32>,
+    entities: Vec<EntityId>,
 }
 
 impl GameScene {
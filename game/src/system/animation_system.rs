@@ -1,12 +1,12 @@
-use engine::ecs::{World, WorldOp};
+use engine::ecs::World;
 
 use crate::components::sprite::Sprite;
 
 pub struct AnimationSystem;
 impl AnimationSystem {
     pub fn tick(world: &World) {
-        for sprite_entity in world.all_with::<Sprite>() {
-            sprite_entity.get::<Sprite>().tick();
+        for (mut sprite,) in world.query::<(&mut Sprite,)>() {
+            sprite.tick();
         }
     }
 }
@@ -5,13 +5,10 @@ use crate::{
     game_state::{self, GameState},
 };
 
-use common::{Debug, Keyboard, Mouse};
-use engine::{
-    graphics::{
-        batch::Batch,
-        common::{PointF, RectF},
-    },
-    Keycode,
+use common::Debug;
+use engine::graphics::{
+    batch::Batch,
+    common::{PointF, RectF},
 };
 use rand::prelude::*;
 
@@ -20,8 +17,11 @@ use crate::game_state::{GAME_TILE_HEIGHT, GAME_TILE_WIDTH, TILE_SIZE};
 use crate::{
     components::room::Room,
     content::Content,
-    game_state::{GAME_PIXEL_HEIGHT, GAME_PIXEL_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH},
+    game_state::{GAME_PIXEL_HEIGHT, GAME_PIXEL_WIDTH},
+    input::Input,
+    mouse,
     target_manager::TargetManager,
+    text_field::TextField,
 };
 
 pub struct Editor {
@@ -30,6 +30,7 @@ pub struct Editor {
     zoom: f32,
     offset: (f32, f32),
     selected_tile: Option<Tile>,
+    command: TextField,
 }
 
 static mut draw_background_tiles: bool = false;
@@ -42,19 +43,32 @@ impl Default for Editor {
             zoom: 1f32,
             offset: (0f32, 0f32),
             selected_tile: None,
+            command: TextField::default(),
         }
     }
 }
 
 impl Editor {
+    // Part of reload::STATE_LAYOUT, list new fields here
+    pub const LAYOUT: u64 =
+        common::struct_layout!(Editor; hover_tile, debug_textures, zoom, offset, selected_tile, command);
+
     pub fn update(&mut self) {
-        if Keyboard::pressed(Keycode::Space) {
+        if self.command.is_open() {
+            if let Some(command) = self.command.update() {
+                self.run_command(&command);
+            }
+        } else if Input::pressed("editor_command") {
+            self.command.open();
+        }
+
+        if Input::pressed("editor_debug_textures") {
             self.debug_textures = !self.debug_textures;
         }
 
-        let mouse_pos = Mouse::position();
+        let mouse_pos = mouse().position();
         let world_mouse = self.screen_to_world((mouse_pos.0, mouse_pos.1));
-        let mouse_rel = Mouse::position_rel();
+        let mouse_rel = mouse().position_rel();
         let world_mouse_rel = (
             mouse_rel.0 as f32 / self.zoom,
             mouse_rel.1 as f32 / self.zoom,
@@ -65,6 +79,11 @@ impl Editor {
             "Room count {}",
             Content::map().rooms.len().to_string()
         ));
+        if self.command.is_open() {
+            Debug::display(&format!("> {}", self.command.display()));
+        } else {
+            Debug::display("Press Return for commands (goto <x> <y>, zoom <level>)");
+        }
         Debug::separator();
         Debug::display(&format!("Zoom level: {:.1}", self.zoom));
         Debug::display(&format!(
@@ -140,7 +159,7 @@ impl Editor {
             );
         }
 
-        if Keyboard::pressed(Keycode::S) {
+        if Input::pressed("editor_save") {
             let rooms = &Content::map().rooms;
             MapData::save(4, 4, rooms);
         }
@@ -195,7 +214,7 @@ impl Editor {
                 w: tile_size,
                 h: tile_size,
             });
-            if Mouse::left_held() {
+            if Input::held("editor_paint") {
                 println!("left_held");
                 room.is_dirty = true;
                 unsafe {
@@ -228,10 +247,10 @@ impl Editor {
         }
 
         // Zoom: https://www.youtube.com/watch?v=ZQ8qtAizis4
-        if Mouse::wheel().1 != 0 {
-            let screen_position = Mouse::position();
+        if mouse().wheel().1 != 0 {
+            let screen_position = mouse().position();
             let before_zoom_world_position = self.screen_to_world(screen_position);
-            self.zoom += Mouse::wheel().1 as f32 * 0.1;
+            self.zoom += mouse().wheel().1 as f32 * 0.1;
             self.zoom = self.zoom.clamp(0.25, 4.0);
             let after_zoom_world_position = self.screen_to_world(screen_position);
 
@@ -240,7 +259,7 @@ impl Editor {
             self.offset.1 +=
                 after_zoom_world_position.1 as f32 - before_zoom_world_position.1 as f32;
         }
-        if Mouse::right_held() {
+        if Input::held("editor_pan") {
             self.offset.0 += world_mouse_rel.0;
             self.offset.1 += world_mouse_rel.1;
         }
@@ -291,7 +310,7 @@ impl Editor {
 
             // Draw editor guidelines, should this be a shader?
             for i in -15..15 {
-                let mut guide = RectF::with_size(1f32, SCREEN_HEIGHT as f32);
+                let mut guide = RectF::with_size(1f32, target_manager.screen.height as f32);
                 guide.translate_by(&PointF::new(
                     (self.offset.0 + (i as f32 * (GAME_PIXEL_WIDTH as f32))) * self.zoom,
                     0f32,
@@ -303,7 +322,7 @@ impl Editor {
                 }
             }
             for i in -15..15 {
-                let mut guide = RectF::with_size(SCREEN_WIDTH as f32, 1f32);
+                let mut guide = RectF::with_size(target_manager.screen.width as f32, 1f32);
                 guide.translate_by(&PointF::new(
                     0f32,
                     (self.offset.1 + (i as f32 * (GAME_PIXEL_HEIGHT as f32))) * self.zoom,
@@ -323,6 +342,24 @@ impl Editor {
         }
     }
 
+    fn run_command(&mut self, command: &str) {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words.as_slice() {
+            ["goto", x, y] => match (x.parse::<f32>(), y.parse::<f32>()) {
+                (Ok(x), Ok(y)) => {
+                    self.offset = (-x * GAME_PIXEL_WIDTH as f32, -y * GAME_PIXEL_HEIGHT as f32);
+                }
+                _ => println!("goto expects room coordinates: goto <x> <y>"),
+            },
+            ["zoom", level] => match level.parse::<f32>() {
+                Ok(level) => self.zoom = level.clamp(0.25, 4.0),
+                Err(_) => println!("zoom expects a number: zoom <level>"),
+            },
+            [] => {}
+            _ => println!("Unknown editor command: {}", command),
+        }
+    }
+
     // Utils
     fn screen_to_world(&self, screen: (i32, i32)) -> (i32, i32) {
         let world = (
@@ -1,6 +1,7 @@
 use crate::{
     components::{
         light::Light,
+        mover::Mover,
         position::Position,
         room::{LayerType, Tile},
     },
@@ -65,13 +66,14 @@ impl LightSystem {
 
         target.clear(base_color);
         // Make the target non-drawable
-        for light_entity in world.all_with::<Light>() {
+        for (_, light_position, mover) in world.query::<(&Light, &Position, Option<&Mover>)>() {
             batch.push_material(&self.material);
             target.clear_stencil(0);
-            let light_offset = light_entity.get::<Light>();
-            let light_position = light_entity.get::<Position>().as_vec2();
-            let light_position =
-                light_position.xy() + glm::vec2(light_offset.offset_x, light_offset.offset_y);
+            // Moving lights (the player's) are drawn between their last two simulated positions, like sprites
+            let light_position = match mover {
+                Some(mover) => mover.interpolate(&light_position),
+                None => light_position.as_vec2(),
+            };
 
             // normalise light position (0 - 1) for the shader
             let ligh_posx = light_position.x - room_position.x as f32;
@@ -225,8 +227,8 @@ impl LightSystem {
             let avgx = [a, b, c, d].map(|f| f.x).iter().sum::<f32>() / 4f32;
             let avgy = [a, b, c, d].map(|f| f.y).iter().sum::<f32>() / 4f32;
 
-            let all_points = [a, b, d, c].iter().copied().map(Into::into).collect();
-            batch.circle_fan((avgx, avgy), &all_points, light_color);
+            let all_points = crate::scratch_slice([a, b, d, c].into_iter().map(Into::into));
+            batch.circle_fan((avgx, avgy), all_points, light_color);
         }
 
         // points[2].x += distance_from_light_norm.x * projection_distance * 2.0;
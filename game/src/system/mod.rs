@@ -6,4 +6,5 @@ pub mod animation_system;
 pub mod scene_system;
 pub mod rope_system;
 pub mod light_system;
+pub mod transform_system;
 pub mod editor;
\ No newline at end of file
@@ -1,7 +1,7 @@
-use std::cell::RefMut;
+use std::collections::BTreeSet;
 
 use engine::{
-    ecs::{World, WorldOp},
+    ecs::{Changed, EntityId, LastRun, Mut, World, WorldOp},
     graphics::common::PointF,
 };
 
@@ -11,21 +11,29 @@ use crate::{
         gravity::Gravity,
         mover::Mover,
         position::Position,
-    }, game_state::TILE_SIZE,
+    }, events::CollisionStarted, game_state::TILE_SIZE,
 };
 
-pub struct MovementSystem;
+#[derive(Default)]
+pub struct MovementSystem {
+    last_run: LastRun,
+    // (entity, other) pairs that were colliding on the last run
+    touching: BTreeSet<(EntityId, EntityId)>,
+}
 impl MovementSystem {
-    pub fn update(&self, world: &mut World) {
-        // Clear all previous collisions
-        world.all_with::<Collider>().for_each(|collider_entity| {
-            collider_entity.get::<Collider>().collisions.clear();
-        });
+    pub fn update(&mut self, world: &mut World) {
+        let since = self.last_run.begin(world);
+        // Clear all previous collisions, only colliders that changed since the last run can have any
+        for (mut collider,) in world.query_filtered::<(&mut Collider,), Changed<Collider>>(since) {
+            if !collider.collisions.is_empty() {
+                collider.collisions.clear();
+            }
+        }
 
         // For everything that moves...
-        for mover_entity in world.all_with::<Mover>() {
-            let mut mover = mover_entity.get::<Mover>();
-            let gravity = mover_entity.has::<Gravity>();
+        for (entity, mut mover, mut position, gravity, collider) in
+            world.query::<(EntityId, &mut Mover, &mut Position, Option<&Gravity>, Option<&mut Collider>)>()
+        {
             if let Some(g) = gravity {
                 if mover.speed.y < 0.0 {
                     // falling down
@@ -47,9 +55,8 @@ impl MovementSystem {
                 mover.reminder.y = total.y - mover.speed.y;
             }
 
-            let mut position = mover_entity.get::<Position>();
+            mover.previous = Some(position.as_vec2());
 
-            let collider = mover_entity.has::<Collider>();
             if collider.is_none() {
                 // Entity has no collider, move it and return early
                 position.x = position.x + mover.speed.x as i32;
@@ -60,7 +67,7 @@ impl MovementSystem {
             collider.collisions.clear();
             MovementSystem::move_x(
                 mover.speed.x as i32,
-                mover_entity.id,
+                entity,
                 &mut collider,
                 &mut position,
                 &mut mover,
@@ -68,21 +75,36 @@ impl MovementSystem {
             );
             MovementSystem::move_y(
                 mover.speed.y as i32,
-                mover_entity.id,
+                entity,
                 &mut collider,
                 &mut position,
                 &mut mover,
                 world,
             );
         }
+
+        // Pairs that weren't colliding on the last run just started to
+        let mut touching = BTreeSet::new();
+        for (entity, collider) in world.query::<(EntityId, &Collider)>() {
+            for collision in collider.collisions.iter() {
+                touching.insert((entity, collision.other));
+            }
+        }
+        for (entity, other) in touching.difference(&self.touching) {
+            world.send_event(CollisionStarted {
+                entity: *entity,
+                other: *other,
+            });
+        }
+        self.touching = touching;
     }
 
     fn move_x(
         amount: i32,
-        entity: u32,
-        collider: &mut RefMut<Collider>,
-        position: &mut RefMut<Position>,
-        mover: &mut RefMut<Mover>,
+        entity: EntityId,
+        collider: &mut Mut<Collider>,
+        position: &mut Mut<Position>,
+        mover: &mut Mut<Mover>,
         world: &World,
     ) {
         let sign_x = amount.signum();
@@ -114,6 +136,8 @@ impl MovementSystem {
                         directions: crate::components::collider::Direction::VERTICAL,
                         self_velocity: glm::Vec2::new(0f32, 0f32),
                     });
+                    // Written through get(), the change tick has to be set by hand
+                    world.mark_changed::<Collider>(collider_entity.id);
 
                     if other_collider.solid {
                         collision = true;
@@ -131,10 +155,10 @@ impl MovementSystem {
 
     pub fn move_y(
         amount: i32,
-        entity: u32,
-        collider: &mut RefMut<Collider>,
-        position: &mut RefMut<Position>,
-        mover: &mut RefMut<Mover>,
+        entity: EntityId,
+        collider: &mut Mut<Collider>,
+        position: &mut Mut<Position>,
+        mover: &mut Mut<Mover>,
         world: &World,
     ) {
         let sign_y = amount.signum();
@@ -167,6 +191,8 @@ impl MovementSystem {
                         directions: crate::components::collider::Direction::VERTICAL,
                         self_velocity: glm::Vec2::new(0f32, 0f32),
                     });
+                    // Written through get(), the change tick has to be set by hand
+                    world.mark_changed::<Collider>(collider_entity.id);
 
                     if other_collider.solid {
                         collision = true;
@@ -1,6 +1,5 @@
-use common::Keyboard;
 use engine::{
-    ecs::{World, WorldOp},
+    ecs::{EventReader, Transform, World, WorldOp},
     graphics::common::{PointF, RectF},
 };
 
@@ -17,16 +16,20 @@ use crate::{
         sprite::Sprite,
     },
     content::{self, Content},
+    events::ButtonPressed,
+    input::Input,
 };
 
-pub struct PlayerSystem;
+#[derive(Default)]
+pub struct PlayerSystem {
+    button_presses: EventReader<ButtonPressed>,
+}
 impl PlayerSystem {
     pub fn init(&self, world: &mut World) {
         let mut player = world.add_entity();
         player.assign(Player::default());
         player.assign(Mover::default());
-        player.assign(Sprite::new(&Content::sprite("output")));
-        player.assign(Light::with_offset(0f32, 8f32));
+        player.assign(Sprite::new("output"));
         player.assign(Collider::new(
             ColliderType::Rect {
                 rect: RectF {
@@ -40,9 +43,21 @@ impl PlayerSystem {
         ));
         player.assign(Position::new( 40 , 40 ));
         player.assign(Gravity { value: 0.2f32 });
+        player.assign(Transform::default());
+        let player = player.id;
+
+        // Follows the player around, see TransformSystem
+        let mut light = world.add_entity();
+        light.assign(Light::new());
+        light.assign(Transform::new(0f32, 8f32));
+        light.assign(Position::default());
+        // Doesn't move on its own, carries the previous position for interpolation
+        light.assign(Mover::default());
+        let light = light.id;
+        world.set_parent(light, player);
     }
 
-    pub fn update(&self, world: &mut World) {
+    pub fn update(&mut self, world: &mut World) {
         let player_entity = world.first::<Player>().expect("Player not found");
 
         let id = player_entity.id;
@@ -76,14 +91,19 @@ impl PlayerSystem {
             // Player just landed
             sprite.scale_x = 1.4f32;
             sprite.scale_y = 0.6f32;
+            crate::gamepad().rumble(0, 0.3, 0.0, 80);
         }
         player.was_in_air = player.in_air;
 
-        if Button::is_pressed(world, "b1") {
+        let b1_pressed = self
+            .button_presses
+            .read(&world.events::<ButtonPressed>())
+            .any(|event| event.name == "b1");
+        if b1_pressed {
             engine::audio().play_sound(&Content::get().tracks["jump"]);
         }
 
-        if Keyboard::pressed(engine::Keycode::Up) || player.jump_buffer > 0 {
+        if Input::pressed("jump") || player.jump_buffer > 0 {
             if !player.in_air || player.coyote_buffer > 0 {
                 // engine::audio().play_sound(&content().tracks["jump"]);
                 sprite.play("jump");
@@ -108,22 +128,16 @@ impl PlayerSystem {
 
         player.update();
         if !player.is_attacking() {
-            if Keyboard::held(&engine::Keycode::Left) {
-                mover.speed.x -= WALK_SPEED;
-                sprite.flip_x = true;
-                if !player.in_air {
-                    sprite.play("run");
-                }
-            }
-            if Keyboard::held(&engine::Keycode::Right) {
-                mover.speed.x += WALK_SPEED;
-                sprite.flip_x = false;
+            let direction = Input::axis("walk");
+            if direction != 0f32 {
+                mover.speed.x += WALK_SPEED * direction;
+                sprite.flip_x = direction < 0f32;
                 if !player.in_air {
                     sprite.play("run");
                 }
             }
         }
-        if Keyboard::held(&engine::Keycode::Space) {
+        if Input::held("attack") {
             player.attack();
         }
         if player.is_attacking() {
@@ -16,10 +16,15 @@ use crate::game_state::{
     SCREEN_WIDTH,
 };
 use crate::{
-    components::{collider::Collider, light::Light, position::Position, sprite::Sprite},
+    components::{
+        collider::Collider, light::Light, mover::Mover, position::Position, sprite::Sprite,
+    },
     game_state::GameState,
 };
 
+// Size of u_light_position in the shader
+const MAX_LIGHTS: usize = 8;
+
 // This shader takes in color + normal (room) textures and multiples them
 pub const FRAGMENT_SHADER_SOURCE: &str = "#version 330 core\n
             in vec2 TexCoord;\n
@@ -88,18 +93,16 @@ impl RenderSystem {
         self.material
             .set_texture("u_normal_texture", room.normal().texture);
 
-        // Normalize light positions
-        let mut light_positions: [f32; 10] = [0.0f32; 10];
-        let lights_in_world = world.all_with::<Light>();
-        let mut light_count = 0;
-        for (i, light_entity) in lights_in_world.enumerate() {
+        // Normalize light positions (x, y pairs), the shader takes up to MAX_LIGHTS
+        let light_count = world.all_with::<Light>().count().min(MAX_LIGHTS);
+        let light_positions = crate::scratch_slice(std::iter::repeat(0.0f32).take(light_count * 2));
+        for (i, light_entity) in world.all_with::<Light>().take(light_count).enumerate() {
             let light_position = light_entity.get::<Position>();
             light_positions[i * 2] = light_position.x as f32 - room.rect.x;
             light_positions[i * 2 + 1] = light_position.y as f32 - room.rect.y;
-            light_count += 1;
         }
         self.material
-            .set_vector2f("u_light_position[0]", &light_positions);
+            .set_vector2f("u_light_position[0]", light_positions);
         self.material.set_valuei("light_count", light_count as i32);
 
         // Render lights
@@ -110,15 +113,19 @@ impl RenderSystem {
         batch.pop_material();
 
         // Lastly, render Sprites
-        for sprite_entity in world.all_with::<Sprite>() {
-            let sprite = sprite_entity.get::<Sprite>();
-            let position = sprite_entity.get::<Position>();
+        for (sprite, position, mover) in world.query::<(&Sprite, &Position, Option<&Mover>)>() {
             let subtexture = sprite.subtexture();
 
+            // Moving entities are drawn between their last two simulated positions
+            let draw_position = match mover {
+                Some(mover) => mover.interpolate(&position),
+                None => position.as_vec2(),
+            };
+
             let pivot = sprite.pivot();
             let pivot_y = -(subtexture.source.h - pivot.1);
             let matrix = create_transform(
-                position.as_vec2(),
+                draw_position,
                 glm::vec2(sprite.scale_x, sprite.scale_y),
                 glm::vec2(pivot.0, pivot_y),
             );
@@ -145,8 +152,6 @@ impl RenderSystem {
         // Only in debug
         Collider::render(&world, batch);
 
-        Debug::display(format!("Current room camera ortho {}", &room.camera_ortho).as_str());
-
         batch.circle((20f32, 20f32), 12f32, 25, (1f32, 1f32, 0f32, 1f32));
         batch.render_with_projection(target, &room.camera_ortho);
         // batch.render(target);
@@ -9,6 +9,7 @@ use crate::{
 pub struct RoomRenderSystem {}
 
 // Re renders maps with stale (dirty) into maps_color (large pre-rendered map texture)
+// Set by whoever edits a room (the editor), see Room::is_dirty for why this isn't change detection
 impl RoomRenderSystem {
     pub fn render(batch : &mut Batch, target_manager: &TargetManager) {
         batch.clear();
@@ -26,7 +27,9 @@ impl RoomRenderSystem {
 
         batch.render(&target_manager.maps_normal); // Render all maps in one go
         batch.clear();
+    }
 
+    pub fn debug(target_manager: &TargetManager) {
         Debug::window("Map textures");
 
         // let tileset = Content::get().tilesets.get(&0).unwrap();
@@ -43,6 +46,5 @@ impl RoomRenderSystem {
         let tileset = Content::get().tilesets.get(&0).unwrap();
         Debug::image("tileset", tileset.texture.id as usize, (tileset.texture.width as f32 * 4f32,
              tileset.texture.height as f32 * 4f32));
-
     }
 }
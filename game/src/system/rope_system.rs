@@ -15,7 +15,7 @@ use crate::components::{
 //     900.0,
 //     glm::vec2(26.0, GAME_PIXEL_HEIGHT as f32 + 30.0 ),
 //     &mut self.world,
-//     0,
+//     None,
 // );
 // let mut prev = point;
 // for i in 1..14 {
@@ -28,7 +28,7 @@ use crate::components::{
 //             GAME_PIXEL_HEIGHT as f32 + 30.0 + i as f32 * 10.0,
 //         ),
 //         &mut self.world,
-//         prev,
+//         Some(prev),
 //     );
 //     prev = point;
 //     rope.add_point(point);
@@ -1,5 +1,6 @@
 use crate::{
     components::{player::Player, position::Position},
+    events::RoomEntered,
     game_state::{GAME_PIXEL_HEIGHT, GAME_PIXEL_WIDTH},
     scene::{GameScene, Scene},
 };
@@ -17,6 +18,9 @@ pub struct SceneSystem {
     pub scene: GameScene,
 }
 impl SceneSystem {
+    // Part of reload::STATE_LAYOUT, list new fields here
+    pub const LAYOUT: u64 = common::struct_layout!(SceneSystem; initialised, scene);
+
     pub fn new() -> Self {
         let (x, y) = (0, 0);
         SceneSystem {
@@ -47,6 +51,10 @@ impl SceneSystem {
             self.scene.destroy(world);
             self.scene = new_scene;
             self.scene.init(world);
+            world.send_event(RoomEntered {
+                x: room_x as i32,
+                y: room_y as i32,
+            });
         }
     }
 }
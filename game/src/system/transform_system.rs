@@ -0,0 +1,31 @@
+use engine::ecs::{GlobalTransform, Parent, Transform, With, Without, World};
+
+use crate::components::{mover::Mover, position::Position};
+
+/**
+ * Keeps Position (what movers and renderers use) in sync with the engine hierarchy.
+ * Roots feed their Position in as their Transform, attached entities get their Position
+ * from the propagated GlobalTransform. Attached entities with a Mover keep their previous
+ * Position so they are interpolated along with their parent.
+ */
+pub struct TransformSystem;
+impl TransformSystem {
+    pub fn update(world: &mut World) {
+        for (position, mut transform) in
+            world.query_filtered::<(&Position, &mut Transform), Without<Parent>>(0)
+        {
+            transform.x = position.x as f32;
+            transform.y = position.y as f32;
+        }
+        world.propagate_transforms();
+        for (global, mut position, mover) in
+            world.query_filtered::<(&GlobalTransform, &mut Position, Option<&mut Mover>), With<Parent>>(0)
+        {
+            if let Some(mut mover) = mover {
+                mover.previous = Some(position.as_vec2());
+            }
+            position.x = global.x.round() as i32;
+            position.y = global.y.round() as i32;
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use engine::graphics::{target::Target, texture::TextureFormat};
+use common::Display;
+use engine::graphics::{common::RectF, target::Target, texture::TextureFormat};
 
 use crate::game_state::{GAME_PIXEL_HEIGHT, GAME_PIXEL_WIDTH, ROOM_COUNT_H, ROOM_COUNT_W, SCREEN_HEIGHT, SCREEN_WIDTH};
 
@@ -16,6 +17,18 @@ pub struct TargetManager {
 }
 
 impl TargetManager {
+    // Part of reload::STATE_LAYOUT, list new fields here
+    pub const LAYOUT: u64 = common::struct_layout!(
+        TargetManager;
+        screen,
+        lights,
+        color,
+        game,
+        maps_color,
+        maps_normal,
+        maps_outline,
+    );
+
     pub fn new() -> Self {
         TargetManager {
             screen: Target::screen(SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32),
@@ -51,4 +64,36 @@ impl TargetManager {
             ),
         }
     }
+
+    // The default framebuffer follows the window, rebuild 'screen' when the drawable size changes
+    pub fn fit_screen(&mut self, display: &Display) {
+        let (width, height) = (display.drawable_size.0 as i32, display.drawable_size.1 as i32);
+        if width <= 0 || height <= 0 {
+            return; // Minimized
+        }
+        if (width, height) != (self.screen.width, self.screen.height) {
+            self.screen = Target::screen(width, height);
+        }
+    }
+
+    // Where 'game' goes on 'screen': largest integer scale that fits, black bars around it
+    pub fn letterbox(display: &Display) -> RectF {
+        let (x, y, scale) = display.letterbox(GAME_PIXEL_WIDTH as u32, GAME_PIXEL_HEIGHT as u32);
+        RectF {
+            x: x as f32,
+            y: y as f32,
+            w: (GAME_PIXEL_WIDTH as u32 * scale) as f32,
+            h: (GAME_PIXEL_HEIGHT as u32 * scale) as f32,
+        }
+    }
+
+    // Maps a drawable position (e.g. the mouse) to a 'game' pixel, None when it falls on the letterbox
+    pub fn screen_to_game(display: &Display, position: (i32, i32)) -> Option<(i32, i32)> {
+        let (x, y, scale) = display.letterbox(GAME_PIXEL_WIDTH as u32, GAME_PIXEL_HEIGHT as u32);
+        let game_x = (position.0 - x).div_euclid(scale as i32);
+        let game_y = (position.1 - y).div_euclid(scale as i32);
+        let inside = (0..GAME_PIXEL_WIDTH as i32).contains(&game_x)
+            && (0..GAME_PIXEL_HEIGHT as i32).contains(&game_y);
+        inside.then_some((game_x, game_y))
+    }
 }
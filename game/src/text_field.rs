@@ -0,0 +1,59 @@
+use common::Scancode;
+
+use crate::{keyboard, text_input};
+
+/**
+ * Single line text field fed by SDL text input.
+ * While a field is open, keyboard bindings of input actions are ignored.
+ */
+#[derive(Default)]
+pub struct TextField {
+    pub text: String,
+    open: bool,
+}
+
+impl TextField {
+    pub fn open(&mut self) {
+        self.text.clear();
+        self.open = true;
+        text_input().start();
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        text_input().stop();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    // Call once per tick. Returns the text once Return is pressed, Escape throws it away
+    pub fn update(&mut self) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+        let keyboard = keyboard();
+        self.text.push_str(text_input().text());
+
+        // The IME handles editing keys itself while composing
+        if text_input().composition().is_empty() {
+            if keyboard.pressed_or_repeated(Scancode::Backspace) {
+                self.text.pop();
+            }
+            if keyboard.pressed(Scancode::Escape) {
+                self.close();
+            }
+            if keyboard.pressed(Scancode::Return) || keyboard.pressed(Scancode::KpEnter) {
+                self.close();
+                return Some(std::mem::take(&mut self.text));
+            }
+        }
+        None
+    }
+
+    // Typed text followed by what the IME is still composing
+    pub fn display(&self) -> String {
+        format!("{}{}_", self.text, text_input().composition())
+    }
+}
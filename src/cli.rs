@@ -0,0 +1,186 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: runtime [options]
+
+  --lib <path>          Game library to load (default: ./target/debug/libgame.<so|dylib|dll>)
+  --title <title>       Window title
+  --scale <n>           Window size as a multiple of the game resolution
+  --fps <n>             Cap the frame rate at n (turns vsync off)
+  --no-reload           Don't watch the game library for changes
+  --frames <n>          Exit after n frames
+  --room <x>,<y>        Start in this room
+  --record <file>       Write the input of every tick to a file (not with --headless)
+  --replay <file>       Feed recorded input instead of the keyboard / mouse
+  --screenshot <file>   Save the last frame as a BMP on exit (and on F12)
+  --headless            Run without a window or imgui (600 frames unless --frames is given)
+  --help                Show this message";
+
+fn default_lib_path() -> PathBuf {
+    let mut path = PathBuf::from("./target/debug/libgame");
+
+    if cfg!(target_os = "windows") {
+        path.set_extension("dll");
+    } else if cfg!(target_os = "macos") {
+        path.set_extension("dylib");
+    } else {
+        path.set_extension("so");
+    }
+    path
+}
+
+/**
+ * Runtime command line options, see USAGE
+ */
+pub struct Options {
+    pub lib_path: PathBuf,
+    pub title: String,
+    pub scale: Option<u32>,
+    pub fps: Option<u32>,
+    pub reload: bool,
+    pub frames: Option<u64>,
+    pub room: Option<(i32, i32)>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub screenshot: Option<PathBuf>,
+    pub headless: bool,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            lib_path: default_lib_path(),
+            title: "Window".to_string(),
+            scale: None,
+            fps: None,
+            reload: true,
+            frames: None,
+            room: None,
+            record: None,
+            replay: None,
+            screenshot: None,
+            headless: false,
+            help: false,
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got '{}'", flag, value))
+}
+
+fn parse_positive(flag: &str, value: &str) -> Result<u32, String> {
+    match parse_number::<u32>(flag, value)? {
+        0 => Err(format!("{} must be greater than 0", flag)),
+        value => Ok(value),
+    }
+}
+
+impl Options {
+    // `args` without the program name
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .map(|value| value.as_str())
+                    .ok_or(format!("{} expects a value", arg))
+            };
+            match arg.as_str() {
+                "--lib" => options.lib_path = PathBuf::from(value()?),
+                "--title" => options.title = value()?.to_string(),
+                "--scale" => options.scale = Some(parse_positive(arg, value()?)?),
+                "--fps" => options.fps = Some(parse_positive(arg, value()?)?),
+                "--no-reload" => options.reload = false,
+                "--frames" => options.frames = Some(parse_number(arg, value()?)?),
+                "--room" => {
+                    let room = value()?;
+                    let (x, y) = room
+                        .split_once(',')
+                        .ok_or(format!("--room expects <x>,<y>, got '{}'", room))?;
+                    options.room = Some((parse_number(arg, x.trim())?, parse_number(arg, y.trim())?));
+                }
+                "--record" => options.record = Some(PathBuf::from(value()?)),
+                "--replay" => options.replay = Some(PathBuf::from(value()?)),
+                "--screenshot" => options.screenshot = Some(PathBuf::from(value()?)),
+                "--headless" => {
+                    options.headless = true;
+                    // Older scripts pass the frame count right after the flag
+                    if let Some(frames) = args.peek().and_then(|frames| frames.parse().ok()) {
+                        options.frames = Some(frames);
+                        args.next();
+                    }
+                }
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("Unknown option '{}'", arg)),
+            }
+        }
+        // There is no live input to record without a window
+        if options.headless && options.record.is_some() {
+            return Err("--record can't be combined with --headless".to_string());
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Options::parse(&args)
+    }
+
+    #[test]
+    fn no_arguments_gives_the_defaults() {
+        let options = parse(&[]).unwrap();
+        assert!(options.reload);
+        assert!(!options.headless);
+        assert_eq!(options.frames, None);
+        assert_eq!(options.lib_path, default_lib_path());
+    }
+
+    #[test]
+    fn flags_and_values() {
+        let options = parse(&[
+            "--lib", "game.so", "--scale", "3", "--no-reload", "--frames", "10", "--room", "1, 2", "--replay", "in.txt",
+        ])
+        .unwrap();
+        assert_eq!(options.lib_path, PathBuf::from("game.so"));
+        assert_eq!(options.scale, Some(3));
+        assert!(!options.reload);
+        assert_eq!(options.frames, Some(10));
+        assert_eq!(options.room, Some((1, 2)));
+        assert_eq!(options.replay, Some(PathBuf::from("in.txt")));
+    }
+
+    #[test]
+    fn headless_takes_an_optional_frame_count() {
+        let options = parse(&["--headless", "30", "--no-reload"]).unwrap();
+        assert!(options.headless);
+        assert_eq!(options.frames, Some(30));
+        let options = parse(&["--headless", "--no-reload"]).unwrap();
+        assert_eq!(options.frames, None);
+        assert!(!options.reload);
+    }
+
+    #[test]
+    fn bad_arguments_are_errors() {
+        assert!(parse(&["--scale", "0"]).is_err());
+        assert!(parse(&["--fps", "fast"]).is_err());
+        assert!(parse(&["--room", "3"]).is_err());
+        assert!(parse(&["--frames"]).is_err());
+        assert!(parse(&["--wat"]).is_err());
+    }
+
+    #[test]
+    fn record_is_rejected_in_headless_mode() {
+        assert!(parse(&["--headless", "--record", "out.txt"]).is_err());
+        assert!(parse(&["--record", "out.txt"]).is_ok());
+    }
+}
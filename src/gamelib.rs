@@ -1,6 +1,11 @@
-use std::{ffi::CString, path::PathBuf};
+use std::{
+    ffi::CString,
+    fmt, fs,
+    path::PathBuf,
+    sync::atomic::{AtomicU32, Ordering},
+};
 use imgui::{SuspendedContext, Ui};
-use common::{GameConfig, GameMemory};
+use common::{GameApiFn, GameConfig, GameMemory, GAME_API_VERSION, GAME_MEMORY_LAYOUT};
 use sdl2::{
     sys::{SDL_LoadFunction, SDL_LoadObject, SDL_UnloadObject},
     AudioSubsystem, VideoSubsystem,
@@ -8,6 +13,7 @@ use sdl2::{
 
 pub type GetConfigFn = extern "C" fn() -> GameConfig;
 pub type UpdateGameFunc = extern "C" fn() -> ();
+pub type RenderGameFunc = extern "C" fn() -> ();
 pub type ClearGameMemFn = extern "C" fn(game_mmory: &mut GameMemory);
 pub type InitGameFunc = extern "C" fn(
     video_subsystem: &VideoSubsystem,
@@ -16,78 +22,120 @@ pub type InitGameFunc = extern "C" fn(
 );
 pub type DeInitGameFunc = extern "C" fn();
 
+// Every load gets its own copy, the dynamic loader caches libraries by path
+static SHADOW_COPIES: AtomicU32 = AtomicU32::new(0);
+
+/**
+ * Copies the built library to a unique path in the temp dir.
+ * The linker can then overwrite the original while the copy stays loaded.
+ */
+fn shadow_copy(path: &PathBuf) -> Result<PathBuf, String> {
+    let copy_index = SHADOW_COPIES.fetch_add(1, Ordering::Relaxed);
+    let file_name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("Invalid path")?;
+    let mut shadow_path = std::env::temp_dir().join(format!(
+        "{}-{}-{}",
+        file_name,
+        std::process::id(),
+        copy_index
+    ));
+    if let Some(extension) = path.extension() {
+        shadow_path.set_extension(extension);
+    }
+    fs::copy(path, &shadow_path).map_err(|e| format!("Failed to copy {:?}: {}", path, e))?;
+    Ok(shadow_path)
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    // Missing or half written library... the same build may load on a later attempt
+    Failed(String),
+    // Built against a different GameApi / GameMemory, loading this build again won't help
+    Incompatible(String),
+}
+
+impl From<String> for LoadError {
+    fn from(error: String) -> Self {
+        LoadError::Failed(error)
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Failed(error) | LoadError::Incompatible(error) => write!(f, "{}", error),
+        }
+    }
+}
+
 pub struct GameLib {
     pub handle: *mut core::ffi::c_void,
+    pub shadow_path: PathBuf,
     pub get_config: GetConfigFn,
     pub update: UpdateGameFunc,
+    pub render: RenderGameFunc,
     pub init: InitGameFunc,
     pub clear_game_mem: ClearGameMemFn,
     pub de_init: DeInitGameFunc,
 }
 
 impl GameLib {
-    pub fn load(path: &PathBuf) -> Result<Self, String> {
-        let c_path =
-            CString::new(path.to_str().ok_or("Invalid path")?).map_err(|e| e.to_string())?;
+    pub fn load(path: &PathBuf) -> Result<Self, LoadError> {
+        let shadow_path = shadow_copy(path)?;
+        let result = Self::load_copy(shadow_path.clone());
+        if result.is_err() {
+            let _ = fs::remove_file(&shadow_path);
+        }
+        result
+    }
+
+    fn load_copy(shadow_path: PathBuf) -> Result<Self, LoadError> {
+        let c_path = CString::new(shadow_path.to_str().ok_or("Invalid path".to_string())?)
+            .map_err(|e| e.to_string())?;
         let handle = unsafe { SDL_LoadObject(c_path.as_ptr()) };
 
         if handle.is_null() {
-            return Err("Failed to load library".to_string());
+            return Err("Failed to load library".to_string().into());
         }
 
-        let func_name = CString::new("get_config").map_err(|e| e.to_string())?;
-        let get_config: GetConfigFn = unsafe {
-            let symbol = SDL_LoadFunction(handle, func_name.as_ptr());
-            if symbol.is_null() {
-                SDL_UnloadObject(handle);
-                return Err("Failed to get_config function".to_string());
-            }
-            std::mem::transmute(symbol)
-        };
-        let func_name = CString::new("update_game").map_err(|e| e.to_string())?;
-        let update: UpdateGameFunc = unsafe {
-            let symbol = SDL_LoadFunction(handle, func_name.as_ptr());
-            if symbol.is_null() {
-                SDL_UnloadObject(handle);
-                return Err("Failed to load function".to_string());
-            }
-            std::mem::transmute(symbol)
-        };
-        let func_name = CString::new("init").map_err(|e| e.to_string())?;
-        let init: InitGameFunc = unsafe {
-            let symbol = SDL_LoadFunction(handle, func_name.as_ptr());
-            if symbol.is_null() {
-                SDL_UnloadObject(handle);
-                return Err("Failed to load init function".to_string());
-            }
-            std::mem::transmute(symbol)
-        };
-        let func_name = CString::new("clear_game").map_err(|e| e.to_string())?;
-        let clear_game: ClearGameMemFn = unsafe {
+        let func_name = CString::new("game_api").map_err(|e| e.to_string())?;
+        let game_api: GameApiFn = unsafe {
             let symbol = SDL_LoadFunction(handle, func_name.as_ptr());
             if symbol.is_null() {
                 SDL_UnloadObject(handle);
-                return Err("Failed to load clear_game function".to_string());
-            }
-            std::mem::transmute(symbol)
-        };
-        let func_name = CString::new("de_init").map_err(|e| e.to_string())?;
-        let de_init: DeInitGameFunc = unsafe {
-            let symbol = SDL_LoadFunction(handle, func_name.as_ptr());
-            if symbol.is_null() {
-                SDL_UnloadObject(handle);
-                return Err("Failed to load de_init function".to_string());
+                return Err("Failed to load game_api function".to_string().into());
             }
             std::mem::transmute(symbol)
         };
 
+        // Check the handshake before calling anything else in the library
+        let api = game_api();
+        if api.version != GAME_API_VERSION {
+            unsafe { SDL_UnloadObject(handle) };
+            return Err(LoadError::Incompatible(format!(
+                "Game API version mismatch: runtime expects v{}, library exports v{}. Rebuild the runtime",
+                GAME_API_VERSION, api.version
+            )));
+        }
+        if api.memory_layout != GAME_MEMORY_LAYOUT {
+            unsafe { SDL_UnloadObject(handle) };
+            return Err(LoadError::Incompatible(format!(
+                "GameMemory layout mismatch: runtime {:#018x}, library {:#018x}. common changed, rebuild the runtime",
+                GAME_MEMORY_LAYOUT, api.memory_layout
+            )));
+        }
+
         Ok(Self {
             handle,
-            get_config,
-            update,
-            init,
-            clear_game_mem: clear_game,
-            de_init,
+            shadow_path,
+            get_config: api.get_config,
+            update: api.update,
+            render: api.render,
+            init: api.init,
+            clear_game_mem: api.clear_game,
+            de_init: api.de_init,
         })
     }
 }
@@ -100,5 +148,6 @@ impl Drop for GameLib {
             (self.de_init)();
             unsafe { SDL_UnloadObject(self.handle) };
         }
+        let _ = fs::remove_file(&self.shadow_path);
     }
 }
@@ -0,0 +1,103 @@
+use common::{Debug, GameMemory};
+use sdl2::video::GLProfile;
+use sdl2::{AudioSubsystem, Sdl, VideoSubsystem};
+
+use crate::cli::Options;
+use crate::gamelib::GameLib;
+use crate::replay::Replay;
+use crate::screenshot;
+
+pub const DEFAULT_FRAMES: u64 = 600;
+
+/**
+ * Runs the game library without a visible window or imgui (CI, build boxes with no display).
+ * SDL is started with the offscreen video driver, so the GL context is backed by EGL
+ * (software rendering through Mesa's llvmpipe when there is no GPU).
+ */
+pub fn run(options: &Options, mut replay: Option<Replay>) {
+    let frames = options.frames.unwrap_or(DEFAULT_FRAMES);
+    sdl2::hint::set("SDL_VIDEODRIVER", "offscreen");
+    sdl2::hint::set("SDL_AUDIODRIVER", "dummy");
+
+    // Create sdl_first, it should be the last thing that gets dropped
+    let sdl_context: Sdl = sdl2::init().unwrap();
+
+    let game = GameLib::load(&options.lib_path).unwrap();
+
+    let config = (game.get_config)();
+    let video_subsystem: VideoSubsystem = sdl_context.video().unwrap();
+    let audio_subsystem: AudioSubsystem = sdl_context.audio().unwrap();
+
+    let gl_attr = video_subsystem.gl_attr();
+    gl_attr.set_context_profile(GLProfile::Core);
+    gl_attr.set_context_version(3, 3);
+
+    let window_size = match options.scale {
+        Some(scale) => (config.game_width * scale, config.game_height * scale),
+        None => (config.window_width, config.window_height),
+    };
+    // Never shown, it only exists to own the offscreen GL context
+    let window = video_subsystem
+        .window(&options.title, window_size.0, window_size.1)
+        .hidden()
+        .opengl()
+        .build()
+        .unwrap();
+    let _ctx = window.gl_create_context().unwrap();
+
+    let mut game_memory = GameMemory::default();
+    game_memory.start_room = options.room;
+
+    Debug::init(&mut game_memory.debug);
+    game_memory.display.window_size = window_size;
+    game_memory.display.drawable_size = window_size;
+
+    (game.init)(&video_subsystem, &audio_subsystem, &mut game_memory);
+    exit_on_fault(&game_memory, 0);
+
+    // No real time here: exactly one tick per frame, as fast as possible
+    game_memory.time.tick_rate = config.tick_rate;
+    game_memory.time.tick_delta = 1.0 / config.tick_rate as f32;
+    game_memory.time.frame_delta = game_memory.time.tick_delta;
+    game_memory.time.ticks_this_frame = 1;
+    game_memory.time.alpha = 1.0;
+
+    for frame in 0..frames {
+        game_memory.scratch.reset();
+        if let Some(replay) = replay.as_mut() {
+            replay.apply_next(&mut game_memory.keyboard, &mut game_memory.mouse, &mut game_memory.gamepad);
+        }
+
+        (game.update)();
+        exit_on_fault(&game_memory, frame);
+        game_memory.time.tick += 1;
+        game_memory.keyboard.end_tick();
+        game_memory.mouse.end_tick();
+        game_memory.gamepad.end_tick();
+        game_memory.text_input.end_tick();
+
+        (game.render)();
+        exit_on_fault(&game_memory, frame);
+
+        // Nobody renders the debug windows, drop them so they don't pile up
+        Debug::clear();
+    }
+    println!("Headless run finished after {} frames", frames);
+
+    // Nothing is ever swapped, the back buffer still holds the last frame
+    if let Some(path) = options.screenshot.as_ref() {
+        if let Err(error) = screenshot::save(&video_subsystem, window_size, path) {
+            println!("Failed to save screenshot: {}", error);
+        }
+    }
+
+    (game.clear_game_mem)(&mut game_memory);
+}
+
+// A headless run is a test, a panic in the game lib fails it
+fn exit_on_fault(game_memory: &GameMemory, frame: u64) {
+    if game_memory.fault.is_set() {
+        eprintln!("Game library panicked on frame {}:\n{}", frame, game_memory.fault.report().unwrap_or_default());
+        std::process::exit(1);
+    }
+}
@@ -1,9 +1,19 @@
 #![allow(warnings)]
 
+mod cli;
 mod gamelib;
-
-use common::{Debug, GameMemory, Keyboard, Mouse};
-use gamelib::GameLib;
+mod headless;
+mod reloader;
+mod replay;
+mod screenshot;
+mod timestep;
+
+use common::{Debug, Display, GameMemory, MAX_GAMEPADS};
+use cli::{Options, USAGE};
+use gamelib::{GameLib, LoadError};
+use reloader::Reloader;
+use replay::{Recorder, Replay};
+use timestep::{FixedTimestep, FrameLimiter};
 use imgui::sys::{
     igGetCurrentContext, igSetAllocatorFunctions, igSetCurrentContext, ImGuiMemAllocFunc,
     ImGuiStorage_SetAllInt,
@@ -12,12 +22,12 @@ use imgui::{Context, SuspendedContext};
 use notify::{Config, Error, RecommendedWatcher, RecursiveMode, Watcher};
 use once_cell::unsync::Lazy;
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::libc::kill;
 use sdl2::sys::SDL_RenderPresent;
-use sdl2::video::GLProfile;
+use sdl2::video::{FullscreenType, GLProfile, SwapInterval, Window};
 use sdl2::{AudioSubsystem, Sdl, VideoSubsystem};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::f64::consts::PI;
 use std::path::PathBuf;
@@ -26,35 +36,129 @@ use std::sync::mpsc::channel;
 use std::sync::mpsc::Receiver;
 use std::time::{Duration, Instant};
 
-pub const FPS: u64 = 60;
-pub const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / FPS);
+// Keeps the game's view of the window in sync, called whenever it may have changed
+fn update_display(window: &Window, display: &mut Display) {
+    display.window_size = window.size();
+    display.drawable_size = window.drawable_size();
+    display.fullscreen = window.fullscreen_state() != FullscreenType::Off;
+}
 
-fn get_lib_path() -> PathBuf {
-    let mut path = PathBuf::from("./target/debug/libgame");
+// Number keys 1 to 9 pick a save state slot
+fn slot_key(keycode: Keycode) -> Option<u8> {
+    let slot = keycode as i32 - Keycode::Num0 as i32;
+    (1..=9).contains(&slot).then_some(slot as u8)
+}
 
-    if cfg!(target_os = "windows") {
-        path.set_extension("dll");
-    } else if cfg!(target_os = "macos") {
-        path.set_extension("dylib");
-    } else {
-        path.set_extension("so");
-    }
-    path
+// Keyboard, mouse and gamepad events that change what the game sees on its next tick
+fn is_device_input(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::KeyDown { .. }
+            | Event::KeyUp { .. }
+            | Event::MouseButtonDown { .. }
+            | Event::MouseButtonUp { .. }
+            | Event::MouseWheel { .. }
+            | Event::MouseMotion { .. }
+            | Event::ControllerButtonDown { .. }
+            | Event::ControllerButtonUp { .. }
+            | Event::ControllerAxisMotion { .. }
+    )
 }
 
-/**
- * Watches the game library for updates
- */
-fn check_for_updates_non_blocking(rx: &Receiver<Result<notify::Event, Error>>) -> bool {
-    let mut updated = false;
-    // Consume all existing file change events, check if at least one event happened
-    while let Ok(_event) = rx.try_recv() {
-        updated = true;
+fn apply_device_input(event: &Event, game_memory: &mut GameMemory) {
+    match event {
+        Event::KeyDown {
+            scancode: Some(scancode),
+            repeat,
+            timestamp,
+            ..
+        } => game_memory.keyboard.key_down(*scancode, *repeat, *timestamp),
+        Event::KeyUp {
+            scancode: Some(scancode),
+            timestamp,
+            ..
+        } => game_memory.keyboard.key_up(*scancode, *timestamp),
+        Event::MouseButtonDown {
+            mouse_btn,
+            timestamp,
+            ..
+        } => game_memory.mouse.button_down(*mouse_btn, *timestamp),
+        Event::MouseButtonUp {
+            mouse_btn,
+            timestamp,
+            ..
+        } => game_memory.mouse.button_up(*mouse_btn, *timestamp),
+        Event::ControllerButtonDown { which, button, .. } => {
+            game_memory.gamepad.press(*which, *button);
+        }
+        Event::ControllerButtonUp { which, button, .. } => {
+            game_memory.gamepad.release(*which, *button);
+        }
+        Event::ControllerAxisMotion {
+            which, axis, value, ..
+        } => {
+            game_memory.gamepad.set_axis(*which, *axis, *value);
+        }
+        Event::MouseWheel { x, y, .. } => {
+            game_memory.mouse.scrolled(*x, *y);
+        }
+        Event::MouseMotion {
+            x, y, xrel, yrel, ..
+        } => {
+            // Window coordinates (top left origin) to drawable pixels (bottom left origin)
+            let display = &game_memory.display;
+            let scale_x = display.drawable_size.0 as f32 / display.window_size.0.max(1) as f32;
+            let scale_y = display.drawable_size.1 as f32 / display.window_size.1.max(1) as f32;
+            let dheight = display.drawable_size.1 as i32;
+            game_memory.mouse.moved(
+                (*x as f32 * scale_x) as i32,
+                dheight - (*y as f32 * scale_y) as i32,
+                (*xrel as f32 * scale_x) as i32,
+                (*yrel as f32 * -scale_y) as i32,
+            );
+        }
+        _ => {}
     }
-    updated
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    // Feed recorded input instead of the SDL keyboard / mouse events
+    let mut replay = options.replay.as_ref().map(|path| {
+        let replay = Replay::load(path).unwrap_or_else(|error| {
+            eprintln!("Failed to load replay {:?}: {}", path, error);
+            std::process::exit(2);
+        });
+        println!("Replaying {} frames from {:?}", replay.len(), path);
+        replay
+    });
+
+    // Run the game lib without a window or imgui
+    if options.headless {
+        headless::run(&options, replay);
+        return;
+    }
+
+    // Write the per-frame input state to a file
+    let mut recorder = options.record.as_ref().map(|path| {
+        Recorder::create(path).unwrap_or_else(|error| {
+            eprintln!("Failed to create recording {:?}: {}", path, error);
+            std::process::exit(2);
+        })
+    });
+
     // Create sdl_first, it should be the last thing that gets dropped
     let sdl_context: Sdl = sdl2::init().unwrap();
 
@@ -62,39 +166,48 @@ fn main() {
     env::set_var("RUST_BACKTRACE", "1");
 
     // Load Game lib
-    let lib_path = get_lib_path();
+    let lib_path = options.lib_path.clone();
     let mut game = GameLib::load(&lib_path).unwrap();
 
     // Watch for game lib updates
-    // TODO: debug only
-    let (tx, rx) = channel();
-    let mut watcher = RecommendedWatcher::new(tx, Config::default()).unwrap();
-    watcher
-        .watch(&lib_path, RecursiveMode::NonRecursive)
-        .unwrap();
+    let mut reloader = options.reload.then(|| Reloader::new(&lib_path));
 
     let mut config = (game.get_config)();
-    let window_size = (config.window_width, config.window_height);
+    let window_size = match options.scale {
+        Some(scale) => (config.game_width * scale, config.game_height * scale),
+        None => (config.window_width, config.window_height),
+    };
     let video_subsystem: VideoSubsystem = sdl_context.video().unwrap();
     let audio_subsystem: AudioSubsystem = sdl_context.audio().unwrap();
+    let controller_subsystem = sdl_context.game_controller().unwrap();
+    // Open controllers by SDL instance id, closed when dropped (on disconnect)
+    let mut controllers: HashMap<u32, sdl2::controller::GameController> = HashMap::new();
 
     let gl_attr = video_subsystem.gl_attr();
     gl_attr.set_context_profile(GLProfile::Core);
     gl_attr.set_context_version(3, 3);
 
-    let window = video_subsystem
-        .window("Window", window_size.0, window_size.1)
+    let mut window = video_subsystem
+        .window(&options.title, window_size.0, window_size.1)
         // .allow_highdpi() TODO bring this back?
         .always_on_top()
+        .resizable()
         .opengl()
         // .borderless()
         .build()
         .unwrap();
 
-    // let drawable_size = window.drawable_size();
-    // let mut screen = Target::screen(drawable_size.0 as i32, drawable_size.1 as i32);
-
     let _ctx = window.gl_create_context().unwrap();
+    // Let the swap block on the display refresh, fall back to sleeping in the loop if unavailable
+    // An explicit frame rate cap replaces vsync
+    let mut frame_limiter = options.fps.map(FrameLimiter::new);
+    let vsync = frame_limiter.is_none()
+        && video_subsystem
+            .gl_set_swap_interval(SwapInterval::VSync)
+            .is_ok();
+    if frame_limiter.is_some() {
+        let _ = video_subsystem.gl_set_swap_interval(SwapInterval::Immediate);
+    }
 
     let mut imgui = Context::create();
     let mut imgui_sdl2 = imgui_sdl2::ImguiSdl2::new(&mut imgui, &window);
@@ -121,12 +234,14 @@ fn main() {
     debug_assert_eq!(gl_attr.context_version(), (3, 3));
 
     let mut game_memory = GameMemory::default();
+    game_memory.start_room = options.room;
 
     let mut events = sdl_context.event_pump().unwrap();
 
-    Keyboard::init(&mut game_memory.keyboard);
-    Mouse::init(&mut game_memory.mouse);
     Debug::init(&mut game_memory.debug);
+    update_display(&window, &mut game_memory.display);
+    // SDL starts with text input on, the game turns it on when it needs it
+    video_subsystem.text_input().stop();
 
     (game.init)(&video_subsystem, &audio_subsystem, &mut game_memory);
 
@@ -135,17 +250,46 @@ fn main() {
     // io.config_flags |= imgui::ConfigFlags::VIEWPORTS_ENABLE;
     //.config_flags |= imgui::ConfigFlags::DOCKING_ENABLE;
 
+    let mut timestep = FixedTimestep::new(config.tick_rate);
+
+    // Device input that arrived while the simulation was paused, applied before the next tick that runs
+    let mut held_input: Vec<Event> = Vec::new();
+
+    let mut frame: u64 = 0;
     'game_loop: loop {
-        // Reload game if needed
-        if check_for_updates_non_blocking(&rx) {
-            game = GameLib::load(&lib_path).unwrap();
-            (game.init)(&video_subsystem, &audio_subsystem, &mut game_memory);
+        if options.frames.is_some_and(|frames| frame >= frames) {
+            println!("Exiting after {} frames", frame);
+            break;
         }
+        frame += 1;
+        let mut take_screenshot = false;
 
-        let start = Instant::now();
-
-        Keyboard::clear_pressed();
-        Mouse::clear();
+        // Reload game if needed
+        if reloader.as_mut().is_some_and(|reloader| reloader.poll()) {
+            match GameLib::load(&lib_path) {
+                Ok(new_game) => {
+                    // The debug windows hold closures compiled into the previous library
+                    Debug::clear();
+                    // Dropping the previous library de-inits and unloads it
+                    game = new_game;
+                    let faulted = game_memory.fault.is_set();
+                    game_memory.fault.clear();
+                    (game.init)(&video_subsystem, &audio_subsystem, &mut game_memory);
+                    println!("Reloaded {:?}", lib_path);
+                    if faulted && !game_memory.fault.is_set() {
+                        println!("Game library recovered, resuming");
+                    }
+                }
+                Err(error) => {
+                    // Keep running the previous library until a loadable one shows up
+                    println!("Failed to reload {:?}: {}", lib_path, error);
+                    // An incompatible build stays incompatible, wait for the next one
+                    if !matches!(error, LoadError::Incompatible(_)) {
+                        reloader.as_mut().unwrap().retry();
+                    }
+                }
+            }
+        }
 
         for ref event in events.poll_iter() {
             imgui_sdl2.handle_event(&mut imgui, &event);
@@ -160,61 +304,202 @@ fn main() {
                     win_event,
                 } => {
                     match win_event {
-                        sdl2::event::WindowEvent::Resized(_w, _hh) => {
-                            let _drawable_size = window.drawable_size();
-                            // screen = Target::screen(drawable_size.0 as i32, drawable_size.1 as i32);
+                        // The game rebuilds its screen target from the new size on the next render
+                        sdl2::event::WindowEvent::SizeChanged(..) => {
+                            update_display(&window, &mut game_memory.display);
                         }
                         _ => {
                             // no op
                         }
                     }
                 }
-                Event::Quit { .. }
-                | Event::KeyDown {
+                Event::Quit { .. } => {
+                    break 'game_loop;
+                }
+                // While the game takes text, Escape belongs to the text field
+                Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
-                } => {
+                } if !game_memory.text_input.active => {
                     break 'game_loop;
                 }
                 Event::KeyDown {
-                    keycode: Some(kc), ..
-                } => Keyboard::press(kc.clone()),
-                Event::KeyUp {
-                    keycode: Some(kc), ..
-                } => Keyboard::release(&kc),
-                Event::MouseButtonDown { mouse_btn, .. } => match mouse_btn {
-                    sdl2::mouse::MouseButton::Left => {
-                        Mouse::press_left();
-                    }
-                    sdl2::mouse::MouseButton::Right => {
-                        Mouse::press_right();
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } if options.screenshot.is_some() => {
+                    take_screenshot = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => {
+                    let fullscreen = match window.fullscreen_state() {
+                        FullscreenType::Off => FullscreenType::Desktop,
+                        _ => FullscreenType::Off,
+                    };
+                    if let Err(error) = window.set_fullscreen(fullscreen) {
+                        println!("Failed to toggle fullscreen: {}", error);
                     }
-                    _ => {}
-                },
-                Event::MouseButtonUp { mouse_btn, .. } => match mouse_btn {
-                    sdl2::mouse::MouseButton::Left => {
-                        Mouse::release_left();
+                    update_display(&window, &mut game_memory.display);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    repeat: false,
+                    ..
+                } => game_memory.playback.toggle_pause(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F6),
+                    ..
+                } => game_memory.playback.step(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F7),
+                    repeat: false,
+                    ..
+                } => game_memory.playback.cycle_slowdown(),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) && slot_key(*keycode).is_some() => {
+                    game_memory.save_slots.save = slot_key(*keycode);
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) && slot_key(*keycode).is_some() => {
+                    game_memory.save_slots.load = slot_key(*keycode);
+                }
+                // Controllers already plugged in at startup show up here too
+                Event::ControllerDeviceAdded { which, .. } => {
+                    match controller_subsystem.open(*which) {
+                        Ok(controller) => {
+                            let instance_id = controller.instance_id();
+                            match game_memory.gamepad.connect(instance_id) {
+                                Some(slot) => {
+                                    println!("Gamepad {} connected: {}", slot, controller.name());
+                                    controllers.insert(instance_id, controller);
+                                }
+                                None => println!(
+                                    "Ignoring {}, already {} gamepads connected",
+                                    controller.name(),
+                                    MAX_GAMEPADS
+                                ),
+                            }
+                        }
+                        Err(error) => println!("Failed to open controller {}: {}", which, error),
                     }
-                    sdl2::mouse::MouseButton::Right => {
-                        Mouse::release_right();
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    if let Some(slot) = game_memory.gamepad.disconnect(*which) {
+                        println!("Gamepad {} disconnected", slot);
                     }
-                    _ => {}
-                },
-                Event::MouseWheel { x, y, .. } => {
-                    Mouse::set_wheel(*x, *y);
+                    controllers.remove(which);
+                }
+                // While replaying, keyboard / mouse / gamepad input comes from the replay file
+                _ if replay.as_ref().is_some_and(|replay| !replay.finished()) => {}
+                Event::TextInput { text, .. } => {
+                    game_memory.text_input.push_text(text);
                 }
-                Event::MouseMotion {
-                    x, y, xrel, yrel, ..
+                Event::TextEditing {
+                    text,
+                    start,
+                    length,
+                    ..
                 } => {
-                    let wheight = window_size.1 as i32;
-                    Mouse::set_position(*x, wheight - *y, *xrel, *yrel * -1);
+                    game_memory.text_input.set_composition(text, *start, *length);
+                }
+                // While paused, input waits for the next tick that runs (F6 step or unpause)
+                _ if is_device_input(event) && (game_memory.playback.paused || !held_input.is_empty()) => {
+                    held_input.push(event.clone());
+                }
+                _ => apply_device_input(event, &mut game_memory),
+            }
+        }
+
+        game_memory.scratch.reset();
+        let mut ticks = timestep.begin_frame(&mut game_memory.time);
+        if game_memory.fault.is_set() {
+            // The game state may be half updated, nothing runs until a fixed library is loaded
+            ticks = 0;
+        }
+        // Every tick that runs rebuilds the debug windows, while paused the old ones stay up
+        for _ in 0..ticks {
+            if !game_memory.playback.should_tick() {
+                continue;
+            }
+            Debug::clear();
+            for event in held_input.drain(..) {
+                apply_device_input(&event, &mut game_memory);
+            }
+            if let Some(replay) = replay.as_mut() {
+                let applied = replay.apply_next(
+                    &mut game_memory.keyboard,
+                    &mut game_memory.mouse,
+                    &mut game_memory.gamepad,
+                );
+                if applied && replay.finished() {
+                    println!("Replay finished after {} ticks, back to live input", replay.current);
                 }
-                _ => {}
+            }
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(&game_memory.keyboard, &game_memory.mouse, &game_memory.gamepad);
+            }
+
+            (game.update)();
+            game_memory.time.tick += 1;
+
+            // One time events (key presses, relative mouse motion) are consumed by the first tick
+            game_memory.keyboard.end_tick();
+            game_memory.mouse.end_tick();
+            game_memory.gamepad.end_tick();
+            game_memory.text_input.end_tick();
+
+            if game_memory.fault.is_set() {
+                break;
+            }
+        }
+        game_memory.time.alpha = game_memory.playback.alpha(game_memory.time.alpha);
+        if !game_memory.fault.is_set() {
+            (game.render)();
+        }
+        // The last frame of a --frames run is saved too, before imgui draws over it
+        let last_frame = options.frames.is_some_and(|frames| frame >= frames);
+        if let Some(path) = options.screenshot.as_ref().filter(|_| take_screenshot || last_frame) {
+            if let Err(error) = screenshot::save(&video_subsystem, window.drawable_size(), path) {
+                println!("Failed to save screenshot: {}", error);
+            }
+        }
+
+        // Text input requested (or released) by the game during this frame
+        let text_input = &mut game_memory.text_input;
+        if text_input.requested != text_input.active {
+            if text_input.requested {
+                video_subsystem.text_input().start();
+            } else {
+                video_subsystem.text_input().stop();
+                text_input.set_composition("", 0, 0);
+            }
+            text_input.active = text_input.requested;
+        }
+
+        // Rumble requested by the game during this frame
+        for (instance_id, controller) in controllers.iter_mut() {
+            let Some(slot) = game_memory.gamepad.slot(*instance_id) else {
+                continue;
+            };
+            if let Some(rumble) = game_memory.gamepad.take_rumble(slot) {
+                let low = (rumble.low_frequency.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+                let high = (rumble.high_frequency.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+                // Not every controller can rumble
+                let _ = controller.set_rumble(low, high, rumble.duration_ms);
             }
         }
 
-        (game.update)();
-        // Update
         let io = imgui.io_mut();
         imgui_sdl2.prepare_frame(io, &window, &events.mouse_state());
 
@@ -223,16 +508,26 @@ fn main() {
 
         imgui_sdl2.prepare_render(&ui, &window);
 
-        if !Debug::is_empty() {
-            Debug::render(ui);
+        if game_memory.fault.is_set() {
+            // The debug windows may belong to the code that just panicked
+            ui.window("Game library panicked")
+                .size([600.0, 400.0], imgui::Condition::FirstUseEver)
+                .build(|| {
+                    ui.text("Fix the code and rebuild, the game resumes once the new library loads.");
+                    ui.separator();
+                    ui.text_wrapped(game_memory.fault.report().unwrap_or_default());
+                });
+            renderer.render(&mut imgui);
+        } else if !Debug::is_empty() {
+            Debug::render(ui, &mut game_memory.playback, &game_memory.time);
             renderer.render(&mut imgui);
         }
         window.gl_swap_window();
-        Debug::clear();
 
-        let sleep_until = start + FRAME_DURATION;
-        while Instant::now() < sleep_until {
-            // sleep
+        if let Some(frame_limiter) = frame_limiter.as_mut() {
+            frame_limiter.wait();
+        } else if !vsync {
+            std::thread::sleep(timestep.time_until_next_tick());
         }
     }
     (game.clear_game_mem)(&mut game_memory);
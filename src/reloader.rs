@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant, SystemTime};
+
+use notify::{Config, Error, RecommendedWatcher, RecursiveMode, Watcher};
+
+// Wait this long after the last write before loading, the linker writes the library in several steps
+pub const RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+// A library that fails to load is retried a few times before waiting for the next build
+// (not one that loaded but is incompatible, see LoadError)
+pub const RELOAD_ATTEMPTS: u32 = 5;
+
+/**
+ * Watches the game library for updates and decides when it is safe to load it again.
+ */
+pub struct Reloader {
+    lib_path: PathBuf,
+    rx: Receiver<Result<notify::Event, Error>>,
+    // Kept alive for as long as we want events
+    _watcher: RecommendedWatcher,
+    // Last time the library was seen changing (file event or different size / modification time)
+    changed_at: Option<Instant>,
+    last_metadata: Option<(u64, SystemTime)>,
+    attempts: u32,
+}
+
+impl Reloader {
+    pub fn new(lib_path: &Path) -> Self {
+        let (tx, rx) = channel();
+        let mut watcher = RecommendedWatcher::new(tx, Config::default()).unwrap();
+        // Watch the directory, the linker may replace the file (new inode) instead of writing into it
+        let directory = lib_path.parent().unwrap_or(Path::new("."));
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .unwrap();
+
+        Reloader {
+            lib_path: lib_path.to_path_buf(),
+            rx,
+            _watcher: watcher,
+            changed_at: None,
+            last_metadata: None,
+            attempts: 0,
+        }
+    }
+
+    fn metadata(&self) -> Option<(u64, SystemTime)> {
+        let metadata = fs::metadata(&self.lib_path).ok()?;
+        Some((metadata.len(), metadata.modified().ok()?))
+    }
+
+    /**
+     * Non blocking, returns true once the library changed and has been left alone for RELOAD_DEBOUNCE
+     */
+    pub fn poll(&mut self) -> bool {
+        // Consume all existing file change events, only the ones about our library count
+        while let Ok(event) = self.rx.try_recv() {
+            let Ok(event) = event else { continue };
+            if event.paths.iter().any(|path| path.file_name() == self.lib_path.file_name()) {
+                self.changed_at = Some(Instant::now());
+                self.attempts = 0;
+            }
+        }
+
+        let Some(changed_at) = self.changed_at else {
+            return false;
+        };
+
+        // Still being written?
+        let metadata = self.metadata();
+        if metadata.is_none() || metadata != self.last_metadata {
+            self.last_metadata = metadata;
+            self.changed_at = Some(Instant::now());
+            return false;
+        }
+
+        if changed_at.elapsed() < RELOAD_DEBOUNCE {
+            return false;
+        }
+        self.changed_at = None;
+        self.attempts += 1;
+        true
+    }
+
+    /**
+     * The library could not be loaded, try again later (up to RELOAD_ATTEMPTS times)
+     */
+    pub fn retry(&mut self) {
+        if self.attempts < RELOAD_ATTEMPTS {
+            self.changed_at = Some(Instant::now());
+        } else {
+            println!(
+                "Giving up on {:?} after {} attempts, waiting for the next build",
+                self.lib_path, self.attempts
+            );
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use common::{Gamepad, KeySet, Keyboard, Mouse, MouseButton, Scancode, MAX_GAMEPADS};
+
+// Bump this if the line format below changes
+const REPLAY_VERSION: &str = "replay v4";
+
+const MOUSE_BUTTONS: [MouseButton; 5] = [
+    MouseButton::Left,
+    MouseButton::Middle,
+    MouseButton::Right,
+    MouseButton::X1,
+    MouseButton::X2,
+];
+
+/**
+ * Input state for a single simulation tick, as it was written into GameMemory.
+ * The one time sets (pressed / released / repeated) are stored, not just what is held:
+ * a key that goes down and up within a tick is only seen through them.
+ *
+ * Stored as one line of text per tick:
+ * `<held>;<pressed>;<released>;<repeated> | <x> <y> <xrel> <yrel> <wheel x> <wheel y> <held> <pressed> <released> | <pads>`
+ * Key sets are comma separated raw SDL scancodes, mouse buttons are the Mouse bitsets.
+ * Pads are `;` separated, one per slot: `<connected> <held> <pressed> <axes...>`, buttons as bitsets.
+ * None of it depends on the game lib so a replay survives hot reloads.
+ */
+#[derive(Default, Clone, PartialEq)]
+pub struct InputFrame {
+    pub held: KeySet,
+    pub pressed: KeySet,
+    pub released: KeySet,
+    pub repeated: KeySet,
+    pub position: (i32, i32),
+    pub position_rel: (i32, i32),
+    pub wheel: (i32, i32),
+    pub mouse_held: u8,
+    pub mouse_pressed: u8,
+    pub mouse_released: u8,
+    pub pads: [PadFrame; MAX_GAMEPADS],
+}
+
+// One gamepad slot, buttons are bitsets indexed by GamepadButton
+#[derive(Default, Clone, PartialEq)]
+pub struct PadFrame {
+    pub connected: bool,
+    pub held: u32,
+    pub pressed: u32,
+    pub axes: Vec<f32>,
+}
+
+fn buttons_to_bits(buttons: &[bool]) -> u32 {
+    buttons
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (index, down)| bits | ((*down as u32) << index))
+}
+
+impl PadFrame {
+    fn to_string(&self) -> String {
+        let axes: Vec<String> = self.axes.iter().map(|axis| axis.to_string()).collect();
+        format!("{} {} {} {}", self.connected as u8, self.held, self.pressed, axes.join(" "))
+    }
+
+    fn from_str(pad: &str) -> Result<Self, String> {
+        let values: Vec<&str> = pad.split_whitespace().collect();
+        if values.len() < 3 {
+            return Err(format!("Malformed gamepad: {}", pad));
+        }
+        let int = |value: &str| value.parse::<u32>().map_err(|e| e.to_string());
+        Ok(PadFrame {
+            connected: int(values[0])? != 0,
+            held: int(values[1])?,
+            pressed: int(values[2])?,
+            axes: values[3..]
+                .iter()
+                .map(|axis| axis.parse::<f32>().map_err(|e| e.to_string()))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+fn keys_to_string(keys: &KeySet) -> String {
+    let keys: Vec<String> = keys.iter().map(|key| (key as i32).to_string()).collect();
+    keys.join(",")
+}
+
+fn keys_from_str(keys: &str) -> Result<KeySet, String> {
+    let mut set = KeySet::default();
+    for key in keys.split(',').map(str::trim).filter(|key| !key.is_empty()) {
+        let code: i32 = key.parse().map_err(|_| format!("Invalid scancode: {}", key))?;
+        set.insert(Scancode::from_i32(code).ok_or(format!("Unknown scancode: {}", code))?);
+    }
+    Ok(set)
+}
+
+/**
+ * The down (true) / up (false) calls that take a key or button from `was_held` to the recorded tick state.
+ * One both pressed and released went through both, the order follows from where it started.
+ */
+fn transitions(was_held: bool, pressed: bool, released: bool, held: bool) -> Vec<bool> {
+    match (was_held, pressed, released) {
+        (true, true, true) if held => vec![false, true],
+        (true, true, true) => vec![false, true, false],
+        (false, true, true) if held => vec![true, false, true],
+        (false, true, true) => vec![true, false],
+        (_, true, false) => vec![true],
+        (_, false, true) => vec![false],
+        _ => vec![],
+    }
+}
+
+impl InputFrame {
+    pub fn capture(keyboard: &Keyboard, mouse: &Mouse, gamepad: &Gamepad) -> Self {
+        InputFrame {
+            held: keyboard.held,
+            pressed: keyboard.pressed,
+            released: keyboard.released,
+            repeated: keyboard.repeated,
+            position: mouse.position,
+            position_rel: mouse.position_rel,
+            wheel: mouse.wheel,
+            mouse_held: mouse.held,
+            mouse_pressed: mouse.pressed,
+            mouse_released: mouse.released,
+            pads: std::array::from_fn(|slot| {
+                let pad = &gamepad.pads[slot];
+                PadFrame {
+                    connected: pad.connected,
+                    held: buttons_to_bits(&pad.held),
+                    pressed: buttons_to_bits(&pad.pressed),
+                    axes: pad.axes.to_vec(),
+                }
+            }),
+        }
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "{};{};{};{} | {} {} {} {} {} {} {} {} {} | {}",
+            keys_to_string(&self.held),
+            keys_to_string(&self.pressed),
+            keys_to_string(&self.released),
+            keys_to_string(&self.repeated),
+            self.position.0,
+            self.position.1,
+            self.position_rel.0,
+            self.position_rel.1,
+            self.wheel.0,
+            self.wheel.1,
+            self.mouse_held,
+            self.mouse_pressed,
+            self.mouse_released,
+            self.pads.iter().map(PadFrame::to_string).collect::<Vec<_>>().join(";"),
+        )
+    }
+
+    fn from_line(line: &str) -> Result<Self, String> {
+        let sections: Vec<&str> = line.split('|').collect();
+        let [keys, mouse, pads] = sections[..] else {
+            return Err(format!("Malformed replay line: {}", line));
+        };
+
+        let keys: Vec<&str> = keys.split(';').collect();
+        if keys.len() != 4 {
+            return Err(format!("Malformed replay line: {}", line));
+        }
+
+        let values: Vec<i32> = mouse
+            .split_whitespace()
+            .map(|value| value.parse::<i32>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        if values.len() != 9 {
+            return Err(format!("Malformed replay line: {}", line));
+        }
+
+        let pads: Vec<PadFrame> = pads.split(';').map(PadFrame::from_str).collect::<Result<_, _>>()?;
+        let pads: [PadFrame; MAX_GAMEPADS] = pads
+            .try_into()
+            .map_err(|_| format!("Replay line doesn't have {} gamepads: {}", MAX_GAMEPADS, line))?;
+
+        Ok(InputFrame {
+            held: keys_from_str(keys[0])?,
+            pressed: keys_from_str(keys[1])?,
+            released: keys_from_str(keys[2])?,
+            repeated: keys_from_str(keys[3])?,
+            position: (values[0], values[1]),
+            position_rel: (values[2], values[3]),
+            wheel: (values[4], values[5]),
+            mouse_held: values[6] as u8,
+            mouse_pressed: values[7] as u8,
+            mouse_released: values[8] as u8,
+            pads,
+        })
+    }
+}
+
+/**
+ * Writes the input state of every tick to a file
+ */
+pub struct Recorder {
+    writer: BufWriter<File>,
+    pub frames: u64,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| e.to_string())?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{}", REPLAY_VERSION).map_err(|e| e.to_string())?;
+        Ok(Recorder { writer, frames: 0 })
+    }
+
+    pub fn record(&mut self, keyboard: &Keyboard, mouse: &Mouse, gamepad: &Gamepad) {
+        let frame = InputFrame::capture(keyboard, mouse, gamepad);
+        writeln!(self.writer, "{}", frame.to_line()).expect("Failed to write replay frame");
+        self.frames += 1;
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+        println!("Recorded {} ticks", self.frames);
+    }
+}
+
+/**
+ * Feeds recorded input back into the Keyboard / Mouse / Gamepad, one tick at a time.
+ * Used in place of the SDL event pump.
+ */
+pub struct Replay {
+    frames: Vec<InputFrame>,
+    pub current: usize,
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| e.to_string())?;
+        let mut lines = BufReader::new(file).lines();
+
+        let header = lines
+            .next()
+            .ok_or("Empty replay file")?
+            .map_err(|e| e.to_string())?;
+        if header.trim() != REPLAY_VERSION {
+            return Err(format!(
+                "Unsupported replay version: {} (expected {})",
+                header, REPLAY_VERSION
+            ));
+        }
+
+        let mut frames = Vec::new();
+        for line in lines {
+            let line = line.map_err(|e| e.to_string())?;
+            frames.push(InputFrame::from_line(&line)?);
+        }
+
+        Ok(Replay {
+            frames,
+            current: 0,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.current >= self.frames.len()
+    }
+
+    /**
+     * Applies the next recorded tick. Must be called after the end_tick() of every device
+     * Returns false once the replay has run out of frames
+     */
+    pub fn apply_next(&mut self, keyboard: &mut Keyboard, mouse: &mut Mouse, gamepad: &mut Gamepad) -> bool {
+        let Some(frame) = self.frames.get(self.current) else {
+            return false;
+        };
+        self.current += 1;
+
+        // Drive the same entry points the SDL events use so pressed / released / held behave identically
+        // Timestamps aren't recorded, replayed transitions all happen at 0
+        for index in 0..common::KEY_COUNT {
+            let Some(key) = Scancode::from_i32(index as i32) else {
+                continue;
+            };
+            let was_held = keyboard.held.contains(key);
+            let (pressed, released, held) = (frame.pressed.contains(key), frame.released.contains(key), frame.held.contains(key));
+            for down in transitions(was_held, pressed, released, held) {
+                if down {
+                    keyboard.key_down(key, false, 0);
+                } else {
+                    keyboard.key_up(key, 0);
+                }
+            }
+            if frame.repeated.contains(key) {
+                keyboard.key_down(key, true, 0);
+            }
+        }
+        // The recorded sets are what the game saw, they win over anything the calls above can't reproduce
+        // (keys that were already down when the recording started)
+        keyboard.held = frame.held;
+        keyboard.pressed = frame.pressed;
+        keyboard.released = frame.released;
+        keyboard.repeated = frame.repeated;
+
+        for button in MOUSE_BUTTONS {
+            let bit = 1 << button as u8;
+            let was_held = mouse.held & bit != 0;
+            let (pressed, released, held) = (frame.mouse_pressed & bit != 0, frame.mouse_released & bit != 0, frame.mouse_held & bit != 0);
+            for down in transitions(was_held, pressed, released, held) {
+                if down {
+                    mouse.button_down(button, 0);
+                } else {
+                    mouse.button_up(button, 0);
+                }
+            }
+        }
+        mouse.held = frame.mouse_held;
+        mouse.pressed = frame.mouse_pressed;
+        mouse.released = frame.mouse_released;
+
+        mouse.moved(
+            frame.position.0,
+            frame.position.1,
+            frame.position_rel.0,
+            frame.position_rel.1,
+        );
+        mouse.scrolled(frame.wheel.0, frame.wheel.1);
+
+        // Pads are written as they were, connected controllers only keep their instance id (rumble)
+        for (pad, recorded) in gamepad.pads.iter_mut().zip(frame.pads.iter()) {
+            pad.connected = recorded.connected;
+            for button in 0..pad.held.len() {
+                pad.held[button] = recorded.held & (1 << button) != 0;
+                pad.pressed[button] = recorded.pressed & (1 << button) != 0;
+            }
+            for (axis, value) in pad.axes.iter_mut().zip(recorded.axes.iter()) {
+                *axis = *value;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{GamepadAxis, GamepadButton};
+
+    #[test]
+    fn tap_within_a_tick_survives_the_round_trip() {
+        let mut keyboard = Keyboard::default();
+        let mut mouse = Mouse::default();
+        keyboard.key_down(Scancode::Space, false, 10);
+        keyboard.key_up(Scancode::Space, 12);
+        mouse.button_down(MouseButton::Left, 10);
+        mouse.button_up(MouseButton::Left, 11);
+        let line = InputFrame::capture(&keyboard, &mouse, &Gamepad::default()).to_line();
+
+        let mut replay = Replay {
+            frames: vec![InputFrame::from_line(&line).unwrap()],
+            current: 0,
+        };
+        let mut replayed_keyboard = Keyboard::default();
+        let mut replayed_mouse = Mouse::default();
+        assert!(replay.apply_next(&mut replayed_keyboard, &mut replayed_mouse, &mut Gamepad::default()));
+        assert!(replayed_keyboard.pressed(Scancode::Space));
+        assert!(replayed_keyboard.released(Scancode::Space));
+        assert!(!replayed_keyboard.held(Scancode::Space));
+        assert_eq!(replayed_keyboard.transitions().len(), 2);
+        assert!(replayed_mouse.left_pressed());
+        assert!(!replayed_mouse.left_held());
+    }
+
+    #[test]
+    fn release_and_press_again_of_a_held_key() {
+        assert_eq!(transitions(true, true, true, true), vec![false, true]);
+        assert_eq!(transitions(false, true, true, false), vec![true, false]);
+        assert_eq!(transitions(false, false, false, true), Vec::<bool>::new());
+    }
+
+    #[test]
+    fn gamepad_survives_the_round_trip() {
+        let mut gamepad = Gamepad::default();
+        gamepad.connect(7);
+        gamepad.press(7, GamepadButton::A);
+        gamepad.set_axis(7, GamepadAxis::LeftX, i16::MAX / 3 * 2);
+        let line = InputFrame::capture(&Keyboard::default(), &Mouse::default(), &gamepad).to_line();
+
+        let mut replay = Replay {
+            frames: vec![InputFrame::from_line(&line).unwrap()],
+            current: 0,
+        };
+        let mut replayed = Gamepad::default();
+        replay.apply_next(&mut Keyboard::default(), &mut Mouse::default(), &mut replayed);
+        assert!(replayed.connected(0));
+        assert!(replayed.any_pressed(GamepadButton::A));
+        assert!(replayed.any_held(GamepadButton::A));
+        assert_eq!(replayed.any_axis(GamepadAxis::LeftX), gamepad.any_axis(GamepadAxis::LeftX));
+    }
+}
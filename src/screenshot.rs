@@ -0,0 +1,45 @@
+use std::{ffi::c_void, path::Path};
+
+use sdl2::{pixels::PixelFormatEnum, surface::Surface, VideoSubsystem};
+
+// The runtime doesn't link the gl crate, glReadPixels is looked up through SDL
+type ReadPixelsFn = extern "system" fn(i32, i32, i32, i32, u32, u32, *mut c_void);
+const GL_RGBA: u32 = 0x1908;
+const GL_UNSIGNED_BYTE: u32 = 0x1401;
+
+/**
+ * Saves the current contents of the default framebuffer as a BMP.
+ * Call before swapping, while the back buffer still holds the frame.
+ */
+pub fn save(video_subsystem: &VideoSubsystem, size: (u32, u32), path: &Path) -> Result<(), String> {
+    let read_pixels = video_subsystem.gl_get_proc_address("glReadPixels");
+    if read_pixels.is_null() {
+        return Err("glReadPixels is not available".to_string());
+    }
+    let read_pixels: ReadPixelsFn = unsafe { std::mem::transmute(read_pixels) };
+
+    let (width, height) = size;
+    let pitch = width as usize * 4;
+    let mut pixels = vec![0u8; pitch * height as usize];
+    read_pixels(
+        0,
+        0,
+        width as i32,
+        height as i32,
+        GL_RGBA,
+        GL_UNSIGNED_BYTE,
+        pixels.as_mut_ptr() as *mut c_void,
+    );
+
+    // GL rows go bottom to top
+    let mut flipped = Vec::with_capacity(pixels.len());
+    for row in pixels.chunks_exact(pitch).rev() {
+        flipped.extend_from_slice(row);
+    }
+
+    // ABGR8888 is R, G, B, A in memory on little endian machines, the same order glReadPixels wrote
+    let surface = Surface::from_data(&mut flipped, width, height, pitch as u32, PixelFormatEnum::ABGR8888)?;
+    surface.save_bmp(path)?;
+    println!("Saved screenshot to {:?}", path);
+    Ok(())
+}
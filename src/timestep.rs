@@ -0,0 +1,96 @@
+use std::time::{Duration, Instant};
+
+use common::Time;
+
+// Frames longer than this (breakpoints, window drags, slow reloads) are not caught up on
+pub const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+// Spiral of death clamp: if the simulation can't keep up, drop time instead of ticking forever
+pub const MAX_TICKS_PER_FRAME: u32 = 8;
+
+/**
+ * Fixed timestep accumulator (https://gafferongames.com/post/fix_your_timestep/)
+ * The simulation always advances in steps of 1 / tick_rate seconds, rendering happens once per frame
+ * and gets the leftover time as an interpolation alpha.
+ */
+pub struct FixedTimestep {
+    tick_duration: Duration,
+    accumulator: Duration,
+    last_frame: Instant,
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: u32) -> Self {
+        assert!(tick_rate > 0, "tick_rate must be greater than 0");
+        FixedTimestep {
+            tick_duration: Duration::from_secs_f64(1.0 / tick_rate as f64),
+            accumulator: Duration::ZERO,
+            last_frame: Instant::now(),
+        }
+    }
+
+    /**
+     * Starts a new frame: returns how many ticks to simulate and fills in the timing data
+     */
+    pub fn begin_frame(&mut self, time: &mut Time) -> u32 {
+        let now = Instant::now();
+        let frame_time = (now - self.last_frame).min(MAX_FRAME_TIME);
+        self.last_frame = now;
+        self.accumulator += frame_time;
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick_duration {
+            self.accumulator -= self.tick_duration;
+            ticks += 1;
+            if ticks == MAX_TICKS_PER_FRAME {
+                // Can't keep up, let the simulation run slower than real time
+                self.accumulator = self.accumulator.min(self.tick_duration);
+                break;
+            }
+        }
+
+        time.tick_rate = (1.0 / self.tick_duration.as_secs_f64()).round() as u32;
+        time.tick_delta = self.tick_duration.as_secs_f32();
+        time.frame_delta = frame_time.as_secs_f32();
+        time.ticks_this_frame = ticks;
+        time.alpha = (self.accumulator.as_secs_f32() / time.tick_delta).clamp(0.0, 1.0);
+        ticks
+    }
+
+    /**
+     * How long the runtime can sleep before the next tick is due
+     */
+    pub fn time_until_next_tick(&self) -> Duration {
+        let pending = self.accumulator + self.last_frame.elapsed();
+        self.tick_duration.saturating_sub(pending)
+    }
+}
+
+/**
+ * Caps the frame rate when vsync is off (--fps)
+ */
+pub struct FrameLimiter {
+    frame_duration: Duration,
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(fps: u32) -> Self {
+        assert!(fps > 0, "fps must be greater than 0");
+        FrameLimiter {
+            frame_duration: Duration::from_secs_f64(1.0 / fps as f64),
+            next_frame: Instant::now(),
+        }
+    }
+
+    // Sleeps until the next frame is due
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        if self.next_frame > now {
+            std::thread::sleep(self.next_frame - now);
+            self.next_frame += self.frame_duration;
+        } else {
+            // Running late, don't try to make up for it
+            self.next_frame = now + self.frame_duration;
+        }
+    }
+}